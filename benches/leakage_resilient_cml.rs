@@ -1,80 +1,38 @@
 use criterion::Criterion;
 use criterion::{criterion_group, criterion_main};
 
-use practical_lr::{bls, bb3_cml, ecdsa, schnorr, okamoto_cml};
+use practical_lr::{Params, SignatureScheme, Bls, Bb3Cml, Ecdsa, Schnorr, OkamotoCml};
 
-fn bench_bls12_381(c: &mut Criterion) {
-    let params: [usize; 3] = [254, 508, 2538];
+fn bench_sign<S: SignatureScheme<Params = Params>>(c: &mut Criterion, label: &str, params: &[usize]) {
     let msg = "Hello, world!";
 
-    for par in params {
-        let group_name = format!("CML_BLS(n={})", par);
+    for &par in params {
+        let group_name = format!("CML_{}(n={})", label, par);
         let mut group = c.benchmark_group(&group_name);
 
-        let (sk, _) = bls::keygen(par);
-        // let signature = bls::sign(&sk, &msg.as_bytes());
-        // group.bench_function("KeyGen", |b| b.iter(|| ecdsa::keygen(par)));
-        group.bench_function("Sign", |b| b.iter(|| bls::sign(&sk, &msg.as_bytes())));
-        // group.bench_function("Verify", |b| b.iter(|| bls::verify(&pk, &msg.as_bytes(), &signature)));
+        let params = Params::new(par);
+        let (sk, pk) = S::keygen(&params);
+        group.bench_function("Sign", |b| b.iter(|| S::sign(&params, &sk, &pk, msg.as_bytes())));
+        let esk = S::expand(&params, &sk);
+        group.bench_function("Sign (expanded)", |b| b.iter(|| S::sign_expanded(&esk, &pk, msg.as_bytes())));
 
         group.finish();
     }
+}
 
-    for par in params {
-        let group_name = format!("CML_BB3(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, _) = bb3_cml::keygen(par);
-        // let signature = bb3::sign(&sk, &msg.as_bytes());
-        // group.bench_function("KeyGen", |b| b.iter(|| ecdsa::keygen(par)));
-        group.bench_function("Sign", |b| b.iter(|| bb3_cml::sign(&sk, &msg.as_bytes())));
-        // group.bench_function("Verify", |b| b.iter(|| bb3::verify(&pk, &msg.as_bytes(), &signature)));
-
-        group.finish();
-    }
+fn bench_bls12_381(c: &mut Criterion) {
+    let params: [usize; 3] = [254, 508, 2538];
 
+    bench_sign::<Bls>(c, "BLS", &params);
+    bench_sign::<Bb3Cml>(c, "BB3", &params);
 }
 
 fn bench_curve25519(c: &mut Criterion) {
     let params: [usize; 3] = [148, 294, 1464];
-    let msg = "Hello, world!";
-
-    for par in params {
-        let group_name = format!("CML_ECDSA(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, _) = ecdsa::keygen(par);
-        // let signature = ecdsa::sign(&sk, &msg.as_bytes());
-        // group.bench_function("KeyGen", |b| b.iter(|| ecdsa::keygen(par)));
-        group.bench_function("Sign", |b| b.iter(|| ecdsa::sign(&sk, &msg.as_bytes())));
-        // group.bench_function("Verify", |b| b.iter(|| ecdsa::verify(&pk, &msg.as_bytes(), &signature)));
 
-        group.finish();
-    }
-
-    for par in params {
-        let group_name = format!("CML_Okamoto(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, pk) = okamoto_cml::keygen(par);
-        // let signature = okamoto_cml::sign(&pk, &sk, &msg.as_bytes());
-        group.bench_function("Sign", |b| b.iter(|| okamoto_cml::sign(&pk, &sk, &msg.as_bytes())));
-        // group.bench_function("Verify", |b| b.iter(|| okamoto_cml::verify(&pk, &msg.as_bytes(), &signature)));
-
-        group.finish();
-    }
-
-    for par in params {
-        let group_name = format!("CML_Schnorr(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, pk) = schnorr::keygen(par);
-        // let signature = schnorr::sign(&pk, &sk, &msg.as_bytes());
-        group.bench_function("Sign", |b| b.iter(|| schnorr::sign(&pk, &sk, &msg.as_bytes())));
-        // group.bench_function("Verify", |b| b.iter(|| schnorr::verify(&pk, &msg.as_bytes(), &signature)));
-
-        group.finish();
-    }
+    bench_sign::<Ecdsa>(c, "ECDSA", &params);
+    bench_sign::<OkamotoCml>(c, "Okamoto", &params);
+    bench_sign::<Schnorr>(c, "Schnorr", &params);
 }
 
 criterion_group!(benches, bench_curve25519, bench_bls12_381);
@@ -0,0 +1,32 @@
+use criterion::{BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main};
+
+use practical_lr::okamoto_multi::MultiParams;
+use practical_lr::params::{self, Curve, Leakage, Model};
+use practical_lr::{OkamotoMulti, SignatureScheme};
+
+// Numbers of generators; m = 2 is LR-Okamoto-AIM.
+const BASES: [usize; 6] = [1, 2, 4, 8, 16, 32];
+
+fn bench_okamoto_multi(c: &mut Criterion) {
+    let msg = "Hello, world!";
+    // Leakage rate 1/2 at 128-bit security.
+    let n = params::min_n(Curve::Curve25519, Model::Aim, params::DEFAULT_SECURITY, Leakage::Rate(0.5)).unwrap();
+    let mut group = c.benchmark_group(format!("AIM_Okamoto-Multi(n={})", n));
+
+    for &m in &BASES {
+        let params = MultiParams::new(n, m);
+        let (sk, pk) = OkamotoMulti::keygen(&params);
+        let esk = OkamotoMulti::expand(&params, &sk);
+        let signature = OkamotoMulti::sign(&params, &sk, &pk, msg.as_bytes());
+
+        group.bench_with_input(BenchmarkId::new("Sign", m), &m, |b, _| b.iter(|| OkamotoMulti::sign(&params, &sk, &pk, msg.as_bytes())));
+        group.bench_with_input(BenchmarkId::new("Sign (expanded)", m), &m, |b, _| b.iter(|| OkamotoMulti::sign_expanded(&esk, &pk, msg.as_bytes())));
+        group.bench_with_input(BenchmarkId::new("Verify", m), &m, |b, _| b.iter(|| OkamotoMulti::verify(&params, &pk, msg.as_bytes(), &signature)));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_okamoto_multi);
+criterion_main!(benches);
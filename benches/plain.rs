@@ -1,74 +1,92 @@
 use criterion::Criterion;
 use criterion::{criterion_group, criterion_main};
 
-use practical_lr::{bls, bb3_aim, ecdsa, schnorr, okamoto_aim};
+use practical_lr::{Params, SignatureScheme, Bls, Bb3Aim, Ecdsa, EcdsaP256, EcdsaSecp256k1, Schnorr, OkamotoAim};
+use practical_lr::{bb3_aim, bls, okamoto_aim, schnorr};
 
-fn bench_plain_signature(c: &mut Criterion) {
+fn bench_scheme<S: SignatureScheme<Params = Params>>(c: &mut Criterion, label: &str) {
     let msg = "Hello, world!";
     let par = 1usize;
-    {
-        let group_name = format!("BLS(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, pk) = bls::keygen(par);
-        let signature = bls::sign(&sk, &msg.as_bytes());
-        // group.bench_function("KeyGen", |b| b.iter(|| ecdsa::keygen(par)));
-        group.bench_function("Sign", |b| b.iter(|| bls::sign(&sk, &msg.as_bytes())));
-        group.bench_function("Verify", |b| b.iter(|| bls::verify(&pk, &msg.as_bytes(), &signature)));
-
-        group.finish();
-    }
-
-    {
-        let group_name = format!("BB3(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, pk) = bb3_aim::keygen(par);
-        let signature = bb3_aim::sign(&sk, &msg.as_bytes());
-        // group.bench_function("KeyGen", |b| b.iter(|| ecdsa::keygen(par)));
-        group.bench_function("Sign", |b| b.iter(|| bb3_aim::sign(&sk, &msg.as_bytes())));
-        group.bench_function("Verify", |b| b.iter(|| bb3_aim::verify(&pk, &msg.as_bytes(), &signature)));
-
-        group.finish();
-    }
-
-    {
-        let group_name = format!("ECDSA(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
 
-        let (sk, pk) = ecdsa::keygen(par);
-        let signature = ecdsa::sign(&sk, &msg.as_bytes());
-        // group.bench_function("KeyGen", |b| b.iter(|| ecdsa::keygen(par)));
-        group.bench_function("Sign", |b| b.iter(|| ecdsa::sign(&sk, &msg.as_bytes())));
-        group.bench_function("Verify", |b| b.iter(|| ecdsa::verify(&pk, &msg.as_bytes(), &signature)));
+    let group_name = format!("{}(n={})", label, par);
+    let mut group = c.benchmark_group(&group_name);
 
-        group.finish();
-    }
+    let params = Params::new(par);
+    let (sk, pk) = S::keygen(&params);
+    let signature = S::sign(&params, &sk, &pk, msg.as_bytes());
+    group.bench_function("Sign", |b| b.iter(|| S::sign(&params, &sk, &pk, msg.as_bytes())));
+    group.bench_function("Verify", |b| b.iter(|| S::verify(&params, &pk, msg.as_bytes(), &signature)));
 
-    {
-        let group_name = format!("Okamoto(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
-
-        let (sk, pk) = okamoto_aim::keygen(par);
-        let signature = okamoto_aim::sign(&pk, &sk, &msg.as_bytes());
-        group.bench_function("Sign", |b| b.iter(|| okamoto_aim::sign(&pk, &sk, &msg.as_bytes())));
-        group.bench_function("Verify", |b| b.iter(|| okamoto_aim::verify(&pk, &msg.as_bytes(), &signature)));
+    group.finish();
+}
 
-        group.finish();
-    }
+fn bench_plain_signature(c: &mut Criterion) {
+    bench_scheme::<Bls>(c, "BLS");
+    bench_scheme::<Bb3Aim>(c, "BB3");
+    bench_scheme::<Ecdsa>(c, "ECDSA");
+    bench_scheme::<EcdsaSecp256k1>(c, "ECDSA-secp256k1");
+    bench_scheme::<EcdsaP256>(c, "ECDSA-P256");
+    bench_scheme::<OkamotoAim>(c, "Okamoto");
+    bench_scheme::<Schnorr>(c, "Schnorr");
+}
 
-    {
-        let group_name = format!("Schnorr(n={})", par);
-        let mut group = c.benchmark_group(&group_name);
+fn bench_batch_verify(c: &mut Criterion) {
+    let msg = "Hello, world!".as_bytes();
+    let size = 64;
+    let params = Params::new(1);
+
+    let mut group = c.benchmark_group(format!("Schnorr(n=1, batch={})", size));
+    let entries: Vec<_> = (0..size).map(|_| {
+        let (sk, pk) = Schnorr::keygen(&params);
+        (pk, msg, schnorr::sign_batchable(&Schnorr::expand(&params, &sk), &pk, msg))
+    }).collect();
+    let signatures: Vec<_> = entries.iter().map(|(pk, msg, signature)| signature.to_signature(pk, msg)).collect();
+    group.bench_function("Verify", |b| b.iter(|| entries.iter().zip(&signatures).all(|((pk, msg, _), signature)| Schnorr::verify(&params, pk, msg, signature))));
+    group.bench_function("Verify batch", |b| b.iter(|| schnorr::verify_batch(&entries)));
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("Okamoto(n=1, batch={})", size));
+    let entries: Vec<_> = (0..size).map(|_| {
+        let (sk, pk) = OkamotoAim::keygen(&params);
+        (pk, msg, okamoto_aim::sign_batchable(&OkamotoAim::expand(&params, &sk), &pk, msg))
+    }).collect();
+    let signatures: Vec<_> = entries.iter().map(|(pk, msg, signature)| signature.to_signature(pk, msg)).collect();
+    group.bench_function("Verify", |b| b.iter(|| entries.iter().zip(&signatures).all(|((pk, msg, _), signature)| OkamotoAim::verify(&params, pk, msg, signature))));
+    group.bench_function("Verify batch", |b| b.iter(|| okamoto_aim::verify_batch(&entries)));
+    group.finish();
+}
 
-        let (sk, pk) = schnorr::keygen(par);
-        let signature = schnorr::sign(&pk, &sk, &msg.as_bytes());
-        group.bench_function("Sign", |b| b.iter(|| schnorr::sign(&pk, &sk, &msg.as_bytes())));
-        group.bench_function("Verify", |b| b.iter(|| schnorr::verify(&pk, &msg.as_bytes(), &signature)));
+fn bench_bls_aggregate(c: &mut Criterion) {
+    let size = 64;
+    let messages: Vec<Vec<u8>> = (0..size).map(|i| format!("Hello, world! {}", i).into_bytes()).collect();
+    let keys: Vec<_> = messages.iter().map(|_| bls::keygen(1)).collect();
+    let signatures: Vec<_> = keys.iter().zip(&messages).map(|((sk, _), msg)| bls::sign(sk, msg)).collect();
+    let entries: Vec<_> = keys.iter().zip(&messages).map(|((_, pk), msg)| (*pk, msg.as_slice())).collect();
+    let signature = bls::aggregate(&signatures);
+
+    let mut group = c.benchmark_group(format!("BLS(n=1, aggregate={})", size));
+    group.bench_function("Verify", |b| b.iter(|| entries.iter().zip(&signatures).all(|((pk, msg), signature)| bls::verify(pk, msg, signature))));
+    group.bench_function("Aggregate verify", |b| b.iter(|| bls::aggregate_verify(&entries, &signature)));
+    group.finish();
+}
 
-        group.finish();
-    }
+fn bench_prepared_verify(c: &mut Criterion) {
+    let msg = "Hello, world!".as_bytes();
+
+    let mut group = c.benchmark_group("BLS(n=1)");
+    let (sk, pk) = bls::keygen(1);
+    let signature = bls::sign(&sk, msg);
+    let prepared = bls::PreparedVerifyingKey::new(&pk);
+    group.bench_function("Verify (prepared)", |b| b.iter(|| prepared.verify(msg, &signature)));
+    group.finish();
+
+    let mut group = c.benchmark_group("BB3(n=1)");
+    let (sk, pk) = bb3_aim::keygen(1);
+    let signature = bb3_aim::sign(&sk, msg);
+    let prepared = bb3_aim::PreparedVerifyingKey::new(&pk);
+    group.bench_function("Verify (prepared)", |b| b.iter(|| prepared.verify(msg, &signature)));
+    group.finish();
 }
 
-criterion_group!(benches, bench_plain_signature);
-criterion_main!(benches);
\ No newline at end of file
+criterion_group!(benches, bench_plain_signature, bench_batch_verify, bench_bls_aggregate, bench_prepared_verify);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Batch verification of Ristretto signatures in the commitment form `R || z`.
+//!
+//! Schnorr and both Okamoto variants check `sum_j z_j * B_j == R + c * pk`
+//! for fixed bases `B_j`, so `schnorr::verify_batch`, `okamoto_aim::verify_batch`
+//! and `okamoto_cml::verify_batch` differ only in their bases and challenges.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
+use rand::{CryptoRng, Rng, RngCore};
+
+/// One entry of a batch: `sum_j z[j] * bases[j] == r + c * pk`. `valid` is
+/// false if the entry already failed validation.
+pub(crate) struct Equation {
+    pub pk: RistrettoPoint,
+    pub r: RistrettoPoint,
+    pub c: Scalar,
+    pub z: Vec<Scalar>,
+    pub valid: bool,
+}
+
+/// Checks every equation at once through a random linear combination and a
+/// single multiscalar multiplication. Only if that check fails are the
+/// equations checked one by one, to return the indices of the invalid ones.
+pub(crate) fn verify_with_rng<R: RngCore + CryptoRng>(bases: &[RistrettoPoint], equations: &[Equation], rng: &mut R) -> Result<(), Vec<usize>> {
+    let weights: Vec<Scalar> = equations.iter().map(|_| Scalar::from(rng.gen::<u128>())).collect();
+
+    // sum_i w_i * (sum_j z_ij * B_j - R_i - c_i * pk_i) == 0
+    let z = (0..bases.len()).map(|j| weights.iter().zip(equations).map(|(w, equation)| w * equation.z[j]).sum::<Scalar>());
+    let scalars = z
+        .chain(weights.iter().map(|w| -w))
+        .chain(weights.iter().zip(equations).map(|(w, equation)| -(w * equation.c)));
+    let points = bases.iter().copied()
+        .chain(equations.iter().map(|equation| equation.r))
+        .chain(equations.iter().map(|equation| equation.pk));
+    if equations.iter().all(|equation| equation.valid) && RistrettoPoint::vartime_multiscalar_mul(scalars, points).is_identity() {
+        return Ok(());
+    }
+
+    let failed: Vec<usize> = equations.iter().enumerate()
+        .filter(|(_, equation)| !equation.valid || !holds(bases, equation))
+        .map(|(i, _)| i)
+        .collect();
+    Err(failed)
+}
+
+fn holds(bases: &[RistrettoPoint], equation: &Equation) -> bool {
+    let scalars = equation.z.iter().copied().chain(std::iter::once(-equation.c));
+    let points = bases.iter().copied().chain(std::iter::once(equation.pk));
+    RistrettoPoint::vartime_multiscalar_mul(scalars, points) == equation.r
+}
@@ -1,38 +1,289 @@
-use bls12_381::{Scalar, G1Affine, G2Affine, pairing};
+use bls12_381::{Scalar, G1Affine, G2Affine, G2Prepared};
 use group::Curve;
-use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::{CryptoRng, RngCore, thread_rng};
+use zeroize::{Zeroize, Zeroizing};
 
 
-use super::{aggregate_bls12_381, sample_bls12_381_lambda, hash_tilde_bls12_381, bls12_381_scalar_list_to_bytes, hash_scalar_bls12_381};
+use super::encoding::{check_length, scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar381_from_bytes, g1_from_bytes, g2_from_bytes};
+use super::secret::SecretVec;
+use super::{ByteEncoding, SecretEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_bls12_381, bb3_verify_prepared, sample_bls12_381_lambda_with_rng, hash_tilde_bls12_381, bls12_381_scalar_list_to_bytes, hash_scalar_bls12_381, retry_degenerate, validate_g1_signature, validate_nonempty, validate_g2_public_key};
 
 pub fn keygen(n: usize) -> (Vec<Scalar>, (G2Affine, G2Affine)) {
-    let sk = sample_bls12_381_lambda(n);
-    let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk), n, false);
-    let b_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk), n, true);
-    let u = G2Affine::generator() * aggregate_bls12_381(&a_list, &sk);
-    let v = G2Affine::generator() * aggregate_bls12_381(&b_list, &sk);
-    (sk, (u.to_affine(), v.to_affine()))
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<Scalar>, (G2Affine, G2Affine)) {
+    keygen_inner(&Params::new(n), rng)
 }
 
 pub fn sign(sk: &[Scalar], msg: &[u8]) -> (G1Affine, Scalar) {
-    let mut rng = ThreadRng::default();
+    sign_with_rng(sk, msg, &mut thread_rng())
+}
+
+/// Panics if `sk` is empty.
+pub fn sign_with_rng<R: RngCore + CryptoRng>(sk: &[Scalar], msg: &[u8], rng: &mut R) -> (G1Affine, Scalar) {
+    retry_degenerate(Bb3Aim::NAME, || try_sign_with_rng(sk, msg, rng))
+}
+
+pub fn try_sign_with_rng<R: RngCore + CryptoRng>(sk: &[Scalar], msg: &[u8], rng: &mut R) -> Result<(G1Affine, Scalar), Error> {
+    validate_nonempty(sk)?;
+    sign_inner(&expand_inner(HashTildeVersion::default(), sk), msg, rng)
+}
+
+pub fn verify(pk: &(G2Affine, G2Affine), msg: &[u8], signature: &(G1Affine, Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify(pk: &(G2Affine, G2Affine), msg: &[u8], signature: &(G1Affine, Scalar)) -> Result<(), Error> {
+    PreparedVerifyingKey::new(pk).try_verify(msg, signature)
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<Scalar>, (G2Affine, G2Affine)) {
+    assert_nonempty(params.n);
+    let sk = sample_bls12_381_lambda_with_rng(params.n, rng);
+    let pk = public_key_inner(params.hash_version, &sk);
+    (sk, pk)
+}
+
+fn expand_inner(version: HashTildeVersion, sk: &[Scalar]) -> ExpandedSigningKey {
+    assert_nonempty(sk.len());
     let n = sk.len();
+    let a_list = Zeroizing::new(hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(sk, version), n, false, version));
+    let b_list = Zeroizing::new(hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(sk, version), n, true, version));
+    ExpandedSigningKey { x: aggregate_bls12_381(&a_list, sk), y: aggregate_bls12_381(&b_list, sk) }
+}
+
+fn public_key_inner(version: HashTildeVersion, sk: &[Scalar]) -> (G2Affine, G2Affine) {
+    let esk = expand_inner(version, sk);
+    let u = G2Affine::generator() * esk.x;
+    let v = G2Affine::generator() * esk.y;
+    (u.to_affine(), v.to_affine())
+}
+
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, msg: &[u8], rng: &mut R) -> Result<(G1Affine, Scalar), Error> {
     let msg_hash = hash_scalar_bls12_381(msg);
-    let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk), n, false);
-    let b_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk), n, true);
     let mut r = [0u8; 64];
-    rng.fill(&mut r);
+    rng.fill_bytes(&mut r);
     let r = Scalar::from_bytes_wide(&r);
-    let pow = msg_hash + aggregate_bls12_381(&a_list, &sk) + r * aggregate_bls12_381(&b_list, &sk);
-    ((G1Affine::generator() * pow.invert().unwrap()).to_affine(), r)
+    let pow = Zeroizing::new(msg_hash + esk.x + r * esk.y);
+    let pow_inv: Option<Scalar> = pow.invert().into();
+    let pow_inv = pow_inv.ok_or(Error::DegenerateNonce)?;
+    #[cfg(test)]
+    let pow_inv = if super::fault::hit() { pow_inv.double() } else { pow_inv };
+    Ok(((G1Affine::generator() * pow_inv).to_affine(), r))
 }
 
-pub fn verify(pk: &(G2Affine, G2Affine), msg: &[u8], signature: &(G1Affine, Scalar)) -> bool {
-    let (u, v) = pk;
-    let (s, r) = signature;
-    let msg_hash = hash_scalar_bls12_381(&msg);
-    pairing(s, &(u + (G2Affine::generator() * msg_hash) + (v * r)).to_affine()) == pairing(&G1Affine::generator(), &G2Affine::generator())
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey(pub(crate) SecretVec<Scalar>);
+
+impl SigningKey {
+    pub fn as_scalars(&self) -> &[Scalar] {
+        &self.0
+    }
+}
+
+impl From<Vec<Scalar>> for SigningKey {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, scalar381_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secrets `<a, sk>` and `<b, sk>`.
+#[derive(Clone)]
+pub struct ExpandedSigningKey {
+    x: Scalar,
+    y: Scalar,
+}
+
+impl std::fmt::Debug for ExpandedSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ExpandedSigningKey(..)")
+    }
+}
+
+impl Drop for ExpandedSigningKey {
+    fn drop(&mut self) {
+        self.x.zeroize();
+        self.y.zeroize();
+    }
+}
+
+/// `u || v`, two compressed G2 points, 192 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+    pub u: G2Affine,
+    pub v: G2Affine,
+}
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 192;
+
+    /// Rejects an identity `u` or `v`, and points outside the prime-order
+    /// subgroup.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_g2_public_key(&self.u)?;
+        validate_g2_public_key(&self.v)
+    }
+}
+
+impl From<(G2Affine, G2Affine)> for VerifyingKey {
+    fn from((u, v): (G2Affine, G2Affine)) -> Self {
+        VerifyingKey { u, v }
+    }
+}
+
+impl From<VerifyingKey> for (G2Affine, G2Affine) {
+    fn from(pk: VerifyingKey) -> Self {
+        (pk.u, pk.v)
+    }
+}
+
+/// A public key with `u` and `v` prepared for the Miller loop, for verifying
+/// many signatures under the same key. The key is validated once, when it
+/// is prepared.
+#[derive(Clone, Debug)]
+pub struct PreparedVerifyingKey {
+    u: G2Prepared,
+    v: G2Prepared,
+    valid: Result<(), Error>,
+}
+
+impl PreparedVerifyingKey {
+    pub fn new(pk: &(G2Affine, G2Affine)) -> Self {
+        let valid = VerifyingKey::from(*pk).validate();
+        PreparedVerifyingKey { u: G2Prepared::from(pk.0), v: G2Prepared::from(pk.1), valid }
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &(G1Affine, Scalar)) -> bool {
+        self.try_verify(msg, signature).is_ok()
+    }
+
+    pub fn try_verify(&self, msg: &[u8], signature: &(G1Affine, Scalar)) -> Result<(), Error> {
+        let (s, r) = signature;
+        self.valid?;
+        validate_g1_signature(s)?;
+        bb3_verify_prepared(&self.u, &self.v, msg, s, r)
+    }
+}
+
+impl From<VerifyingKey> for PreparedVerifyingKey {
+    fn from(pk: VerifyingKey) -> Self {
+        PreparedVerifyingKey::new(&pk.into())
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(&self.u.to_compressed());
+        bytes.extend_from_slice(&self.v.to_compressed());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        let pk = VerifyingKey { u: g2_from_bytes(&bytes[..96])?, v: g2_from_bytes(&bytes[96..])? };
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `s || r`, a compressed G1 point followed by a scalar, 80 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub s: G1Affine,
+    pub r: Scalar,
+}
+
+impl Signature {
+    pub const BYTE_LEN: usize = 80;
+}
+
+impl From<(G1Affine, Scalar)> for Signature {
+    fn from((s, r): (G1Affine, Scalar)) -> Self {
+        Signature { s, r }
+    }
+}
+
+impl From<Signature> for (G1Affine, Scalar) {
+    fn from(signature: Signature) -> Self {
+        (signature.s, signature.r)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(&self.s.to_compressed());
+        bytes.extend_from_slice(&self.r.to_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        let s = g1_from_bytes(&bytes[..48])?;
+        validate_g1_signature(&s)?;
+        Ok(Signature { s, r: scalar381_from_bytes(&bytes[48..])? })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+
+pub struct Bb3Aim;
+
+impl SignatureScheme for Bb3Aim {
+    const NAME: &'static str = "LR-BB3-AIM";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), pk.into())
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        public_key_inner(params.hash_version, &sk.0).into()
+    }
+
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, _pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        sign_inner(esk, msg, rng).map(Signature::from)
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&(*pk).into(), msg, &(*signature).into())
+    }
 }
 
 #[cfg(test)]
@@ -43,7 +294,51 @@ mod tests {
         let n = 100;
         let msg = "Hello, world!";
         let (sk, pk) = keygen(n);
-        let signature = sign(&sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let signature = sign(&sk, msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature))
+    }
+
+    #[test]
+    fn test_lr_bb3_aim_prepared_verify() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = Bb3Aim::keygen(&params);
+        let signature = Bb3Aim::sign(&params, &sk, &pk, msg);
+        let prepared = PreparedVerifyingKey::from(pk);
+        assert!(prepared.verify(msg, &signature.into()));
+        assert!(!prepared.verify(b"forged", &signature.into()));
+        assert!(!prepared.verify(msg, &(signature.s, signature.r + Scalar::one())));
+
+        // The unrearranged equation agrees.
+        let msg_hash = hash_scalar_bls12_381(msg);
+        let q = (pk.u + G2Affine::generator() * msg_hash + pk.v * signature.r).to_affine();
+        assert_eq!(bls12_381::pairing(&signature.s, &q), bls12_381::pairing(&G1Affine::generator(), &G2Affine::generator()));
+    }
+
+    #[test]
+    fn test_lr_bb3_aim_try_sign() {
+        let params = Params::new(10);
+        let (sk, pk) = Bb3Aim::keygen(&params);
+        let signature = Bb3Aim::try_sign(&params, &sk, &pk, b"message").unwrap();
+        assert_eq!(Bb3Aim::try_verify(&params, &pk, b"message", &signature), Ok(()));
+        assert_eq!(Bb3Aim::try_verify(&params, &pk, b"other", &signature), Err(Error::SignatureInvalid));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lr_bb3_aim_rejects_invalid_points() {
+        let mut identity = [0u8; 96];
+        identity[0] = 0xc0;
+        let generator = G2Affine::generator().to_compressed();
+        assert_eq!(VerifyingKey::from_bytes(&[&identity[..], &generator[..]].concat()), Err(Error::IdentityPublicKey));
+        let r = Scalar::one().to_bytes();
+        assert_eq!(Signature::from_bytes(&[&G1Affine::identity().to_compressed()[..], &r[..]].concat()), Err(Error::SignatureInvalid));
+
+        let point = crate::bls::tests::g2_outside_subgroup();
+        let msg = b"Hello, world!";
+        let (sk, pk) = keygen(10);
+        let signature = sign(&sk, msg);
+        assert_eq!(try_verify(&(pk.0, point), msg, &signature), Err(Error::MalformedPoint));
+        assert_eq!(VerifyingKey { u: point, v: pk.1 }.validate(), Err(Error::MalformedPoint));
+        assert_eq!(try_verify(&pk, msg, &(G1Affine::identity(), signature.1)), Err(Error::SignatureInvalid));
+    }
+}
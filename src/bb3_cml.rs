@@ -1,40 +1,323 @@
-use bls12_381::{Scalar, G1Affine, G2Affine, pairing};
+use bls12_381::{Scalar, G1Affine, G2Affine, G2Prepared};
 use group::Curve;
-use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::{CryptoRng, RngCore, thread_rng};
+use zeroize::{Zeroize, Zeroizing};
 
+use super::encoding::{check_length, shares_from_bytes, shares_to_bytes, scalar381_from_bytes, g1_from_bytes, g2_from_bytes};
+use super::secret::SecretVec;
+use super::{ByteEncoding, SecretEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_bls12_381, bb3_verify_prepared, refresh_share_bls12_381, sample_bls12_381_lambda_with_rng, hash_tilde_bls12_381, bls12_381_scalar_list_to_bytes, hash_scalar_bls12_381, validate_g1_signature, validate_g2_public_key};
 
-use super::{aggregate_bls12_381, sample_bls12_381_lambda, hash_tilde_bls12_381, bls12_381_scalar_list_to_bytes, hash_scalar_bls12_381};
+pub mod split;
 
 pub fn keygen(n: usize) -> ((Vec<Scalar>, Vec<Scalar>), (G2Affine, G2Affine)) {
-    let sk1 = sample_bls12_381_lambda(n);
-    let sk2 = sample_bls12_381_lambda(n);
-    let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk1), n, false);
-    let b_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk2), n, true);
-    let u = G2Affine::generator() * aggregate_bls12_381(&a_list, &sk1);
-    let v = G2Affine::generator() * aggregate_bls12_381(&b_list, &sk2);
-    ((sk1, sk2), (u.to_affine(), v.to_affine()))
-}
-
-pub fn sign(sk: &(Vec<Scalar>, Vec<Scalar>), msg: &[u8]) -> (G1Affine, Scalar) {
-    let (sk1, sk2) = sk;
-    let mut rng = ThreadRng::default();
-    let n = sk1.len();
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> ((Vec<Scalar>, Vec<Scalar>), (G2Affine, G2Affine)) {
+    let (sk, pk) = keygen_inner(&Params::new(n), rng);
+    ((sk.sk1.into_vec(), sk.sk2.into_vec()), pk)
+}
+
+/// Re-randomizes both shares without changing the public key.
+///
+/// Each share moves by a random vector orthogonal to its coefficient vector,
+/// so the aggregated secrets stay the same while the shares themselves change.
+/// The coefficients of a fresh key are derived under `params.hash_version`.
+/// Panics if `n < 2`.
+pub fn refresh<R: RngCore + CryptoRng>(params: &Params, sk: &SigningKey, rng: &mut R) -> SigningKey {
+    let (a_list, b_list) = sk.coefficients(params.hash_version);
+    SigningKey {
+        sk1: refresh_share_bls12_381(&sk.sk1, &a_list, rng).into(),
+        sk2: refresh_share_bls12_381(&sk.sk2, &b_list, rng).into(),
+        coefficients: Some((SecretVec::from_slice(&a_list), SecretVec::from_slice(&b_list))),
+    }
+}
+
+pub fn verify(pk: &(G2Affine, G2Affine), msg: &[u8], signature: &(G1Affine, Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify(pk: &(G2Affine, G2Affine), msg: &[u8], signature: &(G1Affine, Scalar)) -> Result<(), Error> {
+    PreparedVerifyingKey::new(pk).try_verify(msg, signature)
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, (G2Affine, G2Affine)) {
+    assert_nonempty(params.n);
+    let sk1 = sample_bls12_381_lambda_with_rng(params.n, rng);
+    let sk2 = sample_bls12_381_lambda_with_rng(params.n, rng);
+    let sk = SigningKey::from_shares(sk1, sk2);
+    let pk = sk.public_key(params.hash_version);
+    (sk, pk)
+}
+
+// The coefficients of a share straight from `keygen`, hashed from the share.
+// `variant` tells the coefficients of `sk2` from those of `sk1`.
+fn coefficients(share: &[Scalar], variant: bool, version: HashTildeVersion) -> Zeroizing<Vec<Scalar>> {
+    Zeroizing::new(hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(share, version), share.len(), variant, version))
+}
+
+fn expand_inner(sk1: &[Scalar], a_list: &[Scalar], sk2: &[Scalar], b_list: &[Scalar]) -> ExpandedSigningKey {
+    assert_nonempty(sk1.len());
+    ExpandedSigningKey { x: aggregate_bls12_381(a_list, sk1), y: aggregate_bls12_381(b_list, sk2) }
+}
+
+fn public_key_inner(sk1: &[Scalar], a_list: &[Scalar], sk2: &[Scalar], b_list: &[Scalar]) -> (G2Affine, G2Affine) {
+    let esk = expand_inner(sk1, a_list, sk2, b_list);
+    let u = G2Affine::generator() * esk.x;
+    let v = G2Affine::generator() * esk.y;
+    (u.to_affine(), v.to_affine())
+}
+
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, msg: &[u8], rng: &mut R) -> Result<(G1Affine, Scalar), Error> {
     let msg_hash = hash_scalar_bls12_381(msg);
-    let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk1), n, false);
-    let b_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk2), n, true);
     let mut r = [0u8; 64];
-    rng.fill(&mut r);
+    rng.fill_bytes(&mut r);
     let r = Scalar::from_bytes_wide(&r);
-    let pow = msg_hash + aggregate_bls12_381(&a_list, &sk1) + r * aggregate_bls12_381(&b_list, &sk2);
-    ((G1Affine::generator() * pow.invert().unwrap()).to_affine(), r)
+    let pow = Zeroizing::new(msg_hash + esk.x + r * esk.y);
+    let pow_inv: Option<Scalar> = pow.invert().into();
+    let pow_inv = pow_inv.ok_or(Error::DegenerateNonce)?;
+    #[cfg(test)]
+    let pow_inv = if super::fault::hit() { pow_inv.double() } else { pow_inv };
+    Ok(((G1Affine::generator() * pow_inv).to_affine(), r))
 }
 
-pub fn verify(pk: &(G2Affine, G2Affine), msg: &[u8], signature: &(G1Affine, Scalar)) -> bool {
-    let (u, v) = pk;
-    let (s, r) = signature;
-    let msg_hash = hash_scalar_bls12_381(&msg);
-    pairing(s, &(u + (G2Affine::generator() * msg_hash) + (v * r)).to_affine()) == pairing(&G1Affine::generator(), &G2Affine::generator())
+/// The two shares, and once refreshed the coefficient vectors they are
+/// aggregated with.
+///
+/// The coefficients of a fresh key are hashed from its shares under
+/// `Params::hash_version`. A refreshed share no longer determines them, so
+/// `refresh` stores them in the key. Encoded as `n` (u64 LE) followed by
+/// `sk1` and `sk2`, or by `sk1`, `a_list`, `sk2` and `b_list` once refreshed,
+/// `n` 32-byte scalars each.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    pub(crate) sk1: SecretVec<Scalar>,
+    pub(crate) sk2: SecretVec<Scalar>,
+    pub(crate) coefficients: Option<(SecretVec<Scalar>, SecretVec<Scalar>)>,
+}
+
+impl SigningKey {
+    /// Wraps shares produced by `keygen`. Panics if they differ in length or
+    /// are empty.
+    pub fn from_shares(sk1: Vec<Scalar>, sk2: Vec<Scalar>) -> Self {
+        assert_eq!(sk1.len(), sk2.len());
+        assert_nonempty(sk1.len());
+        SigningKey { sk1: sk1.into(), sk2: sk2.into(), coefficients: None }
+    }
+
+    pub(crate) fn coefficients(&self, version: HashTildeVersion) -> (Zeroizing<Vec<Scalar>>, Zeroizing<Vec<Scalar>>) {
+        match &self.coefficients {
+            Some((a_list, b_list)) => (Zeroizing::new(a_list.to_vec()), Zeroizing::new(b_list.to_vec())),
+            None => (coefficients(&self.sk1, false, version), coefficients(&self.sk2, true, version)),
+        }
+    }
+
+    fn public_key(&self, version: HashTildeVersion) -> (G2Affine, G2Affine) {
+        let (a_list, b_list) = self.coefficients(version);
+        public_key_inner(&self.sk1, &a_list, &self.sk2, &b_list)
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.sk1.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        let coefficients = self.coefficients.as_ref().map(|(a_list, b_list)| [&a_list[..], &b_list[..]]);
+        shares_to_bytes(&[&self.sk1[..], &self.sk2[..]], coefficients.as_ref().map(|c| &c[..]), Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (mut shares, coefficients) = shares_from_bytes(bytes, 2, scalar381_from_bytes)?;
+        let coefficients = coefficients.map(|mut c| {
+            let b_list = c.pop().unwrap();
+            (c.pop().unwrap().into(), b_list.into())
+        });
+        let sk2 = shares.pop().unwrap().into();
+        Ok(SigningKey { sk1: shares.pop().unwrap().into(), sk2, coefficients })
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secrets `<a, sk1>` and `<b, sk2>`.
+#[derive(Clone)]
+pub struct ExpandedSigningKey {
+    x: Scalar,
+    y: Scalar,
+}
+
+impl std::fmt::Debug for ExpandedSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ExpandedSigningKey(..)")
+    }
+}
+
+impl Drop for ExpandedSigningKey {
+    fn drop(&mut self) {
+        self.x.zeroize();
+        self.y.zeroize();
+    }
+}
+
+/// `u || v`, two compressed G2 points, 192 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey {
+    pub u: G2Affine,
+    pub v: G2Affine,
+}
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 192;
+
+    /// Rejects an identity `u` or `v`, and points outside the prime-order
+    /// subgroup.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_g2_public_key(&self.u)?;
+        validate_g2_public_key(&self.v)
+    }
+}
+
+impl From<(G2Affine, G2Affine)> for VerifyingKey {
+    fn from((u, v): (G2Affine, G2Affine)) -> Self {
+        VerifyingKey { u, v }
+    }
+}
+
+impl From<VerifyingKey> for (G2Affine, G2Affine) {
+    fn from(pk: VerifyingKey) -> Self {
+        (pk.u, pk.v)
+    }
+}
+
+/// A public key with `u` and `v` prepared for the Miller loop, for verifying
+/// many signatures under the same key. The key is validated once, when it
+/// is prepared.
+#[derive(Clone, Debug)]
+pub struct PreparedVerifyingKey {
+    u: G2Prepared,
+    v: G2Prepared,
+    valid: Result<(), Error>,
+}
+
+impl PreparedVerifyingKey {
+    pub fn new(pk: &(G2Affine, G2Affine)) -> Self {
+        let valid = VerifyingKey::from(*pk).validate();
+        PreparedVerifyingKey { u: G2Prepared::from(pk.0), v: G2Prepared::from(pk.1), valid }
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &(G1Affine, Scalar)) -> bool {
+        self.try_verify(msg, signature).is_ok()
+    }
+
+    pub fn try_verify(&self, msg: &[u8], signature: &(G1Affine, Scalar)) -> Result<(), Error> {
+        let (s, r) = signature;
+        self.valid?;
+        validate_g1_signature(s)?;
+        bb3_verify_prepared(&self.u, &self.v, msg, s, r)
+    }
+}
+
+impl From<VerifyingKey> for PreparedVerifyingKey {
+    fn from(pk: VerifyingKey) -> Self {
+        PreparedVerifyingKey::new(&pk.into())
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(&self.u.to_compressed());
+        bytes.extend_from_slice(&self.v.to_compressed());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        let pk = VerifyingKey { u: g2_from_bytes(&bytes[..96])?, v: g2_from_bytes(&bytes[96..])? };
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `s || r`, a compressed G1 point followed by a scalar, 80 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub s: G1Affine,
+    pub r: Scalar,
+}
+
+impl Signature {
+    pub const BYTE_LEN: usize = 80;
+}
+
+impl From<(G1Affine, Scalar)> for Signature {
+    fn from((s, r): (G1Affine, Scalar)) -> Self {
+        Signature { s, r }
+    }
+}
+
+impl From<Signature> for (G1Affine, Scalar) {
+    fn from(signature: Signature) -> Self {
+        (signature.s, signature.r)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(&self.s.to_compressed());
+        bytes.extend_from_slice(&self.r.to_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        let s = g1_from_bytes(&bytes[..48])?;
+        validate_g1_signature(&s)?;
+        Ok(Signature { s, r: scalar381_from_bytes(&bytes[48..])? })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+
+pub struct Bb3Cml;
+
+impl SignatureScheme for Bb3Cml {
+    const NAME: &'static str = "LR-BB3-CML";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (sk, pk.into())
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        sk.public_key(params.hash_version).into()
+    }
+
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        let (a_list, b_list) = sk.coefficients(params.hash_version);
+        expand_inner(&sk.sk1, &a_list, &sk.sk2, &b_list)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, _pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        sign_inner(esk, msg, rng).map(Signature::from)
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&(*pk).into(), msg, &(*signature).into())
+    }
 }
 
 #[cfg(test)]
@@ -44,8 +327,72 @@ mod tests {
     fn test_lr_bb3_cml() {
         let n = 100;
         let msg = "Hello, world!";
-        let (sk, pk) = keygen(n);
-        let signature = sign(&sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let ((sk1, sk2), pk) = keygen(n);
+        let signature = Bb3Cml::sign(&Params::new(n), &SigningKey::from_shares(sk1, sk2), &pk.into(), msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature.into()))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lr_bb3_cml_refresh() {
+        let params = Params::new(20);
+        let msg = "Hello, world!";
+        let mut rng = thread_rng();
+        let (mut sk, pk) = Bb3Cml::keygen(&params);
+        for _ in 0..3 {
+            let refreshed = refresh(&params, &sk, &mut rng);
+            assert!(refreshed.sk1.iter().zip(sk.sk1.iter()).all(|(a, b)| a != b));
+            assert!(refreshed.sk2.iter().zip(sk.sk2.iter()).all(|(a, b)| a != b));
+            assert_eq!(Bb3Cml::public_key(&params, &refreshed), pk);
+            let signature = Bb3Cml::sign(&params, &refreshed, &pk, msg.as_bytes());
+            assert!(Bb3Cml::verify(&params, &pk, msg.as_bytes(), &signature));
+            let decoded = SigningKey::from_bytes(&refreshed.to_bytes()).unwrap();
+            assert_eq!(Bb3Cml::public_key(&params, &decoded), pk);
+            sk = refreshed;
+        }
+    }
+
+    #[test]
+    fn test_lr_bb3_cml_legacy_coefficients() {
+        let params = Params { n: 20, hash_version: HashTildeVersion::V0 };
+        let ((sk1, sk2), _) = keygen(params.n);
+        // As derived before keys could be refreshed.
+        let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk1, params.hash_version), params.n, false, params.hash_version);
+        let b_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk2, params.hash_version), params.n, true, params.hash_version);
+        let u = G2Affine::generator() * aggregate_bls12_381(&a_list, &sk1);
+        let v = G2Affine::generator() * aggregate_bls12_381(&b_list, &sk2);
+        let pk = VerifyingKey { u: u.to_affine(), v: v.to_affine() };
+        let sk = SigningKey::from_shares(sk1, sk2);
+        assert_eq!(crate::detect_hash_version::<Bb3Cml>(params.n, &sk, &pk), Some(HashTildeVersion::V0));
+        let refreshed = refresh(&params, &sk, &mut thread_rng());
+        assert_eq!(Bb3Cml::public_key(&params, &refreshed), pk);
+        let msg = b"Hello, world!";
+        assert!(Bb3Cml::verify(&params, &pk, msg, &Bb3Cml::sign(&params, &refreshed, &pk, msg)));
+    }
+
+    #[test]
+    fn test_lr_bb3_cml_prepared_verify() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = Bb3Cml::keygen(&params);
+        let signature = Bb3Cml::sign(&params, &sk, &pk, msg);
+        let prepared = PreparedVerifyingKey::from(pk);
+        assert!(prepared.verify(msg, &signature.into()));
+        assert!(!prepared.verify(b"forged", &signature.into()));
+        assert!(!prepared.verify(msg, &(signature.s, signature.r + Scalar::one())));
+
+        // The unrearranged equation agrees.
+        let msg_hash = hash_scalar_bls12_381(msg);
+        let q = (pk.u + G2Affine::generator() * msg_hash + pk.v * signature.r).to_affine();
+        assert_eq!(bls12_381::pairing(&signature.s, &q), bls12_381::pairing(&G1Affine::generator(), &G2Affine::generator()));
+    }
+
+    #[test]
+    fn test_lr_bb3_cml_rejects_identity_points() {
+        let mut identity = [0u8; 96];
+        identity[0] = 0xc0;
+        let generator = G2Affine::generator().to_compressed();
+        assert_eq!(VerifyingKey::from_bytes(&[&generator[..], &identity[..]].concat()), Err(Error::IdentityPublicKey));
+        let r = Scalar::one().to_bytes();
+        assert_eq!(Signature::from_bytes(&[&G1Affine::identity().to_compressed()[..], &r[..]].concat()), Err(Error::SignatureInvalid));
+    }
+}
@@ -0,0 +1,384 @@
+//! Two-party signing with the halves of a `SigningKey` on separate devices.
+//!
+//! Party 1 holds `sk1` and knows `t_1 = H(m) + x`, party 2 holds `sk2`,
+//! picks `r` and knows `t_2 = r y`. The signature needs `g1^(1/(t_1 + t_2))`,
+//! which neither party may compute alone. Each party masks with a random
+//! scalar, `alpha` for party 1 and `beta` for party 2, and two
+//! multiplicative-to-additive conversions give party 1 the masked sum
+//! `m = alpha beta (t_1 + t_2)`. Party 2 sends `G = g1^(alpha beta)`, and
+//! `s = G^(1/m)`. Party 1 learns only `beta (t_1 + t_2)` and party 2 nothing
+//! about `t_1`.
+//!
+//! 1. Party 1 sends `Round1`: `g1^alpha` and its transfer key.
+//! 2. Party 2 sends `Round2`: `G`, `r`, its choices and its transfer key.
+//! 3. Party 1 sends `Round3`: its ciphertexts and choices.
+//! 4. Party 2 sends `Round4`: its ciphertexts and its share of `m`.
+//! 5. Party 1 computes `s` and checks the signature.
+//!
+//! The messages implement `ByteEncoding` and serde, so any transport can
+//! carry them. The parties are assumed to follow the protocol: it keeps the
+//! shares apart against leakage, not against a malicious counterpart.
+
+use bls12_381::{Scalar, G1Affine};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use group::Curve;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use crate::encoding::{check_length, scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar381_from_bytes, g1_from_bytes, ristretto_from_bytes};
+use crate::mta::{self, OT_COUNT, random_scalar381};
+use crate::secret::SecretVec;
+use crate::{ByteEncoding, SecretEncoding, Error, Params, aggregate_bls12_381, refresh_share_bls12_381, hash_scalar_bls12_381};
+use super::{Signature, SigningKey, VerifyingKey, try_verify};
+
+/// One share with the coefficient vector it is aggregated with. Encoded as
+/// `n` (u64 LE) followed by the share and its coefficients, `n` 32-byte
+/// scalars each.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Share {
+    sk: SecretVec<Scalar>,
+    coefficients: SecretVec<Scalar>,
+}
+
+impl Share {
+    /// Re-randomizes the share without changing its aggregate, so each party
+    /// can refresh on its own. Panics if `n < 2`.
+    pub fn refresh<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Share {
+        let sk = refresh_share_bls12_381(&self.sk, &self.coefficients, rng).into();
+        Share { sk, coefficients: self.coefficients.clone() }
+    }
+
+    fn aggregate(&self) -> Scalar {
+        aggregate_bls12_381(&self.coefficients, &self.sk)
+    }
+}
+
+impl SigningKey {
+    /// The shares of party 1 and party 2, whose coefficients are derived
+    /// under `params.hash_version`.
+    pub fn split(&self, params: &Params) -> (Share, Share) {
+        let (a_list, b_list) = self.coefficients(params.hash_version);
+        (
+            Share { sk: self.sk1.clone(), coefficients: SecretVec::from_slice(&a_list) },
+            Share { sk: self.sk2.clone(), coefficients: SecretVec::from_slice(&b_list) },
+        )
+    }
+}
+
+impl std::fmt::Debug for Share {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Share(n = {})", self.sk.len())
+    }
+}
+
+impl ByteEncoding for Share {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.sk[..], &self.coefficients[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 2, scalar381_from_bytes)?;
+        let coefficients = vectors.pop().unwrap().into();
+        Ok(Share { sk: vectors.pop().unwrap().into(), coefficients })
+    }
+}
+
+impl SecretEncoding for Share {}
+
+fn random_nonzero<R: RngCore + CryptoRng>(rng: &mut R) -> Scalar {
+    loop {
+        let scalar = random_scalar381(rng);
+        if scalar != Scalar::zero() {
+            return scalar;
+        }
+    }
+}
+
+/// Party 1 waiting for `Round2`.
+pub struct Party1 {
+    pk: VerifyingKey,
+    msg: Vec<u8>,
+    alpha: Scalar,
+    mta: mta::Sender,
+}
+
+impl Party1 {
+    pub fn new<R: RngCore + CryptoRng>(share: &Share, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> (Party1, Round1) {
+        let alpha = random_nonzero(rng);
+        let mut t_1 = hash_scalar_bls12_381(msg) + share.aggregate();
+        let mta = mta::Sender::new(alpha * t_1, rng);
+        t_1.zeroize();
+        let round1 = Round1 { g_alpha: (G1Affine::generator() * alpha).to_affine(), key: mta.key() };
+        (Party1 { pk: *pk, msg: msg.to_vec(), alpha, mta }, round1)
+    }
+
+    pub fn receive<R: RngCore + CryptoRng>(self, round2: &Round2, rng: &mut R) -> (Party1Final, Round3) {
+        let (share, ciphertexts) = self.mta.respond(&round2.choices, rng);
+        let mta = mta::Receiver::new(&self.alpha, round2.key, rng);
+        let round3 = Round3 { ciphertexts, choices: mta.choices().to_vec() };
+        let party = Party1Final { pk: self.pk, msg: self.msg.clone(), g: round2.g, r: round2.r, share, mta };
+        (party, round3)
+    }
+}
+
+impl Drop for Party1 {
+    fn drop(&mut self) {
+        self.alpha.zeroize();
+    }
+}
+
+/// Party 1 waiting for `Round4`.
+pub struct Party1Final {
+    pk: VerifyingKey,
+    msg: Vec<u8>,
+    g: G1Affine,
+    r: Scalar,
+    share: Scalar,
+    mta: mta::Receiver,
+}
+
+impl Party1Final {
+    /// Completes the signature, failing with `Error::SignatureInvalid` if it
+    /// does not verify, e.g. because the shares do not belong to `pk`.
+    pub fn finish(self, round4: &Round4) -> Result<Signature, Error> {
+        let m = self.share + self.mta.finish(&round4.ciphertexts) + round4.share;
+        let m_inv: Option<Scalar> = m.invert().into();
+        let m_inv = m_inv.ok_or(Error::DegenerateNonce)?;
+        let signature = Signature { s: (self.g * m_inv).to_affine(), r: self.r };
+        try_verify(&self.pk.into(), &self.msg, &signature.into())?;
+        Ok(signature)
+    }
+}
+
+impl Drop for Party1Final {
+    fn drop(&mut self) {
+        self.share.zeroize();
+    }
+}
+
+/// Party 2 waiting for `Round1`.
+pub struct Party2 {
+    y: Scalar,
+}
+
+impl Party2 {
+    pub fn new(share: &Share) -> Party2 {
+        Party2 { y: share.aggregate() }
+    }
+
+    pub fn receive<R: RngCore + CryptoRng>(self, round1: &Round1, rng: &mut R) -> (Party2Final, Round2) {
+        let r = random_scalar381(rng);
+        let mut beta = random_nonzero(rng);
+        let receiver = mta::Receiver::new(&beta, round1.key, rng);
+        let sender = mta::Sender::new(beta * r * self.y, rng);
+        let round2 = Round2 { g: (round1.g_alpha * beta).to_affine(), r, key: sender.key(), choices: receiver.choices().to_vec() };
+        beta.zeroize();
+        (Party2Final { receiver, sender }, round2)
+    }
+}
+
+impl Drop for Party2 {
+    fn drop(&mut self) {
+        self.y.zeroize();
+    }
+}
+
+/// Party 2 waiting for `Round3`.
+pub struct Party2Final {
+    receiver: mta::Receiver,
+    sender: mta::Sender,
+}
+
+impl Party2Final {
+    pub fn receive<R: RngCore + CryptoRng>(self, round3: &Round3, rng: &mut R) -> Round4 {
+        let received = self.receiver.finish(&round3.ciphertexts);
+        let (sent, ciphertexts) = self.sender.respond(&round3.choices, rng);
+        Round4 { ciphertexts, share: received + sent }
+    }
+}
+
+/// `g1^alpha || key`, 80 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Round1 {
+    g_alpha: G1Affine,
+    key: RistrettoPoint,
+}
+
+impl Round1 {
+    pub const BYTE_LEN: usize = 80;
+}
+
+impl ByteEncoding for Round1 {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(&self.g_alpha.to_compressed());
+        bytes.extend_from_slice(self.key.compress().as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Round1 { g_alpha: g1_from_bytes(&bytes[..48])?, key: ristretto_from_bytes(&bytes[48..])? })
+    }
+}
+
+/// `G || r || key` followed by 255 choices, 8272 bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round2 {
+    g: G1Affine,
+    r: Scalar,
+    key: RistrettoPoint,
+    choices: Vec<RistrettoPoint>,
+}
+
+impl Round2 {
+    pub const BYTE_LEN: usize = 112 + 32 * OT_COUNT;
+}
+
+impl ByteEncoding for Round2 {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(&self.g.to_compressed());
+        bytes.extend_from_slice(&self.r.to_bytes());
+        bytes.extend_from_slice(self.key.compress().as_bytes());
+        write_points(&mut bytes, &self.choices);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Round2 {
+            g: g1_from_bytes(&bytes[..48])?,
+            r: scalar381_from_bytes(&bytes[48..80])?,
+            key: ristretto_from_bytes(&bytes[80..112])?,
+            choices: read_points(&bytes[112..])?,
+        })
+    }
+}
+
+/// 255 ciphertext pairs followed by 255 choices, 24480 bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round3 {
+    ciphertexts: Vec<[Scalar; 2]>,
+    choices: Vec<RistrettoPoint>,
+}
+
+impl Round3 {
+    pub const BYTE_LEN: usize = 96 * OT_COUNT;
+}
+
+impl ByteEncoding for Round3 {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        write_ciphertexts(&mut bytes, &self.ciphertexts);
+        write_points(&mut bytes, &self.choices);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Round3 { ciphertexts: read_ciphertexts(&bytes[..64 * OT_COUNT])?, choices: read_points(&bytes[64 * OT_COUNT..])? })
+    }
+}
+
+/// 255 ciphertext pairs followed by party 2's share of `m`, 16352 bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round4 {
+    ciphertexts: Vec<[Scalar; 2]>,
+    share: Scalar,
+}
+
+impl Round4 {
+    pub const BYTE_LEN: usize = 64 * OT_COUNT + 32;
+}
+
+impl ByteEncoding for Round4 {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        write_ciphertexts(&mut bytes, &self.ciphertexts);
+        bytes.extend_from_slice(&self.share.to_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Round4 { ciphertexts: read_ciphertexts(&bytes[..64 * OT_COUNT])?, share: scalar381_from_bytes(&bytes[64 * OT_COUNT..])? })
+    }
+}
+
+fn write_points(bytes: &mut Vec<u8>, points: &[RistrettoPoint]) {
+    for point in points {
+        bytes.extend_from_slice(point.compress().as_bytes());
+    }
+}
+
+fn read_points(bytes: &[u8]) -> Result<Vec<RistrettoPoint>, Error> {
+    bytes.chunks_exact(32).map(ristretto_from_bytes).collect()
+}
+
+fn write_ciphertexts(bytes: &mut Vec<u8>, ciphertexts: &[[Scalar; 2]]) {
+    for scalar in ciphertexts.iter().flatten() {
+        bytes.extend_from_slice(&scalar.to_bytes());
+    }
+}
+
+fn read_ciphertexts(bytes: &[u8]) -> Result<Vec<[Scalar; 2]>, Error> {
+    bytes.chunks_exact(64).map(|pair| Ok([scalar381_from_bytes(&pair[..32])?, scalar381_from_bytes(&pair[32..])?])).collect()
+}
+
+impl_serde_via_bytes!(Share);
+impl_serde_via_bytes!(Round1);
+impl_serde_via_bytes!(Round2);
+impl_serde_via_bytes!(Round3);
+impl_serde_via_bytes!(Round4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SignatureScheme;
+    use crate::bb3_cml::{Bb3Cml, verify};
+    use rand::thread_rng;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    #[test]
+    fn test_lr_bb3_cml_two_party() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = Bb3Cml::keygen(&params);
+        let (share1, share2) = sk.split(&params);
+        let share2 = share2.refresh(&mut thread_rng()).to_bytes();
+
+        // Party 2 runs on its own thread and only ever sees bytes.
+        let (to_party2, from_party1) = channel::<Vec<u8>>();
+        let (to_party1, from_party2) = channel::<Vec<u8>>();
+        let party2 = thread::spawn(move || {
+            let party = Party2::new(&Share::from_bytes(&share2).unwrap());
+            let round1 = Round1::from_bytes(&from_party1.recv().unwrap()).unwrap();
+            let (party, round2) = party.receive(&round1, &mut thread_rng());
+            to_party1.send(round2.to_bytes()).unwrap();
+            let round3 = Round3::from_bytes(&from_party1.recv().unwrap()).unwrap();
+            to_party1.send(party.receive(&round3, &mut thread_rng()).to_bytes()).unwrap();
+        });
+
+        let (party1, round1) = Party1::new(&share1, &pk, msg, &mut thread_rng());
+        to_party2.send(round1.to_bytes()).unwrap();
+        let round2 = Round2::from_bytes(&from_party2.recv().unwrap()).unwrap();
+        let (party1, round3) = party1.receive(&round2, &mut thread_rng());
+        to_party2.send(round3.to_bytes()).unwrap();
+        let round4 = Round4::from_bytes(&from_party2.recv().unwrap()).unwrap();
+        let signature = party1.finish(&round4).unwrap();
+        party2.join().unwrap();
+
+        assert!(verify(&pk.into(), msg, &signature.into()));
+        assert!(Bb3Cml::verify(&params, &pk, msg, &signature));
+
+        // Shares of another key do not combine.
+        let (other, _) = Bb3Cml::keygen(&params);
+        let (party1, round1) = Party1::new(&share1, &pk, msg, &mut thread_rng());
+        let (party2, round2) = Party2::new(&other.split(&params).1).receive(&round1, &mut thread_rng());
+        let (party1, round3) = party1.receive(&round2, &mut thread_rng());
+        let round4 = party2.receive(&round3, &mut thread_rng());
+        assert_eq!(party1.finish(&round4), Err(Error::SignatureInvalid));
+    }
+}
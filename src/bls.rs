@@ -1,36 +1,569 @@
-use bls12_381::{Scalar, G1Affine, G2Affine, pairing};
-use group::Curve;
+use bls12_381::{Scalar, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, multi_miller_loop};
+use group::{Curve, Group};
+use std::collections::HashSet;
+use rand::{CryptoRng, RngCore, thread_rng};
+use zeroize::{Zeroize, Zeroizing};
 
-use super::{aggregate_bls12_381, sample_bls12_381_lambda, hash_tilde_bls12_381, bls12_381_scalar_list_to_bytes, hash_g1_bls12_381};
+use super::encoding::{scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar381_from_bytes, g1_from_bytes, g2_from_bytes};
+use super::secret::SecretVec;
+use super::{ByteEncoding, SecretEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_bls12_381, sample_bls12_381_lambda_with_rng, hash_tilde_bls12_381, bls12_381_scalar_list_to_bytes, encode_g1_bls12_381_legacy, hash_g1_bls12_381_with_domain, validate_g1_signature, validate_g2_public_key};
 
 pub fn keygen(n: usize) -> (Vec<Scalar>, G2Affine) {
-    let sk = sample_bls12_381_lambda(n);
-    let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk), n, false);
-    let pk = G2Affine::generator() * aggregate_bls12_381(&a_list, &sk);
-    (sk, pk.to_affine())
+    keygen_with_rng(n, &mut thread_rng())
 }
 
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<Scalar>, G2Affine) {
+    keygen_inner(&Params::new(n), rng)
+}
+
+/// RFC 9380 domain separation tag for hashing messages, that of the IETF BLS
+/// ciphersuite with signatures in G1. Applications that want their signatures
+/// to be useless elsewhere pass their own tag to the `*_with_dst` functions.
+pub const DEFAULT_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+
+lazy_static::lazy_static! {
+    static ref NEG_G2_PREPARED: G2Prepared = G2Prepared::from(-G2Affine::generator());
+}
+
+/// Tag for hashing public keys in proofs of possession, kept apart from message signing.
+pub const POP_DOMAIN: &[u8] = b"BLS_POP_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Tag for hashing messages in the proof-of-possession scheme, which signers
+/// whose signatures go to `fast_aggregate_verify` or `AggregatePublicKey` sign
+/// under with `sign_with_dst`.
+pub const POP_SCHEME_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+
 pub fn sign(sk: &[Scalar], msg: &[u8]) -> G1Affine {
-    let n = sk.len();
-    let msg_hash = hash_g1_bls12_381(&msg);
-    let a_list = hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(&sk), n, false);
-    (msg_hash * aggregate_bls12_381(&a_list, &sk)).to_affine()
+    sign_with_dst(sk, msg, DEFAULT_DST)
+}
+
+pub fn sign_with_dst(sk: &[Scalar], msg: &[u8], dst: &[u8]) -> G1Affine {
+    sign_expanded_with_dst(&expand_inner(HashTildeVersion::default(), sk), msg, dst)
+}
+
+pub fn sign_expanded_with_dst(esk: &ExpandedSigningKey, msg: &[u8], dst: &[u8]) -> G1Affine {
+    (hash_g1_bls12_381_with_domain(msg, dst) * esk.x).to_affine()
+}
+
+/// Signs the public key under `POP_DOMAIN`, proving knowledge of its secret.
+pub fn prove_possession(sk: &[Scalar]) -> G1Affine {
+    prove_possession_expanded(&expand_inner(HashTildeVersion::default(), sk))
+}
+
+pub fn prove_possession_expanded(esk: &ExpandedSigningKey) -> G1Affine {
+    let pk = (G2Affine::generator() * esk.x).to_affine();
+    sign_expanded_with_dst(esk, &pk.to_compressed(), POP_DOMAIN)
+}
+
+pub fn verify_possession(pk: &G2Affine, proof: &G1Affine) -> bool {
+    try_verify_possession(pk, proof).is_ok()
+}
+
+pub fn try_verify_possession(pk: &G2Affine, proof: &G1Affine) -> Result<(), Error> {
+    try_verify_with_dst(pk, &pk.to_compressed(), proof, POP_DOMAIN)
 }
 
 pub fn verify(pk: &G2Affine, msg: &[u8], signature: &G1Affine) -> bool {
-    let msg_hash = hash_g1_bls12_381(&msg);
-    pairing(signature, &G2Affine::generator()) == pairing(&msg_hash, pk)
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify(pk: &G2Affine, msg: &[u8], signature: &G1Affine) -> Result<(), Error> {
+    try_verify_with_dst(pk, msg, signature, DEFAULT_DST)
+}
+
+pub fn verify_with_dst(pk: &G2Affine, msg: &[u8], signature: &G1Affine, dst: &[u8]) -> bool {
+    try_verify_with_dst(pk, msg, signature, dst).is_ok()
+}
+
+pub fn try_verify_with_dst(pk: &G2Affine, msg: &[u8], signature: &G1Affine, dst: &[u8]) -> Result<(), Error> {
+    validate_g2_public_key(pk)?;
+    validate_g1_signature(signature)?;
+    verify_hashed(&G2Prepared::from(*pk), &hash_g1_bls12_381_with_domain(msg, dst), signature)
+}
+
+/// Checks a signature made before messages were hashed with RFC 9380
+/// `hash_to_curve`, when `encode_to_curve` and the tag `"test_domain"` were used.
+pub fn try_verify_legacy(pk: &G2Affine, msg: &[u8], signature: &G1Affine) -> Result<(), Error> {
+    validate_g2_public_key(pk)?;
+    validate_g1_signature(signature)?;
+    verify_hashed(&G2Prepared::from(*pk), &encode_g1_bls12_381_legacy(msg), signature)
+}
+
+// e(signature, -g2) * e(H(m), pk) == 1
+fn verify_hashed(pk: &G2Prepared, msg_hash: &G1Affine, signature: &G1Affine) -> Result<(), Error> {
+    pairing_product_is_identity(&[(signature, &*NEG_G2_PREPARED), (msg_hash, pk)])
+}
+
+/// Sums signatures into a single G1 point.
+pub fn aggregate(signatures: &[G1Affine]) -> G1Affine {
+    signatures.iter().fold(G1Projective::identity(), |acc, signature| acc + signature).to_affine()
+}
+
+pub fn aggregate_verify(entries: &[(G2Affine, &[u8])], signature: &G1Affine) -> bool {
+    try_aggregate_verify(entries, signature).is_ok()
+}
+
+pub fn try_aggregate_verify(entries: &[(G2Affine, &[u8])], signature: &G1Affine) -> Result<(), Error> {
+    try_aggregate_verify_with_dst(entries, signature, DEFAULT_DST)
+}
+
+/// Checks an aggregate of signatures by `pk_i` on `msg_i`, with one multi-Miller
+/// loop and one final exponentiation.
+///
+/// Without proofs of possession an adversary can choose a public key that
+/// cancels out the others, so, as in the IETF basic scheme, the messages must
+/// all be distinct and `Error::DuplicateMessage` is returned otherwise.
+/// Same-message multisignatures go through `fast_aggregate_verify`.
+pub fn try_aggregate_verify_with_dst(entries: &[(G2Affine, &[u8])], signature: &G1Affine, dst: &[u8]) -> Result<(), Error> {
+    if entries.is_empty() {
+        return Err(Error::SignatureInvalid);
+    }
+    let mut messages = HashSet::with_capacity(entries.len());
+    if !entries.iter().all(|(_, msg)| messages.insert(*msg)) {
+        return Err(Error::DuplicateMessage);
+    }
+    for (pk, _) in entries {
+        validate_g2_public_key(pk)?;
+    }
+    validate_g1_signature(signature)?;
+    let msg_hashes: Vec<G1Affine> = entries.iter().map(|(_, msg)| hash_g1_bls12_381_with_domain(msg, dst)).collect();
+    let pks: Vec<G2Prepared> = entries.iter().map(|(pk, _)| G2Prepared::from(*pk)).collect();
+    let terms: Vec<(&G1Affine, &G2Prepared)> = std::iter::once((signature, &*NEG_G2_PREPARED)).chain(msg_hashes.iter().zip(pks.iter())).collect();
+    pairing_product_is_identity(&terms)
+}
+
+pub fn fast_aggregate_verify(pks: &[VerifiedPublicKey], msg: &[u8], signature: &G1Affine) -> bool {
+    try_fast_aggregate_verify(pks, msg, signature).is_ok()
+}
+
+pub fn try_fast_aggregate_verify(pks: &[VerifiedPublicKey], msg: &[u8], signature: &G1Affine) -> Result<(), Error> {
+    try_fast_aggregate_verify_with_dst(pks, msg, signature, POP_SCHEME_DST)
+}
+
+/// Checks an aggregate of signatures on the same message, with two pairings
+/// whatever the number of signers.
+///
+/// Summing the keys lets a rogue key cancel out the others, so only keys
+/// whose proof of possession has been checked are accepted.
+pub fn try_fast_aggregate_verify_with_dst(pks: &[VerifiedPublicKey], msg: &[u8], signature: &G1Affine, dst: &[u8]) -> Result<(), Error> {
+    if pks.is_empty() {
+        return Err(Error::SignatureInvalid);
+    }
+    AggregatePublicKey::new(pks).try_verify_with_dst(msg, signature, dst)
+}
+
+// prod_i e(p_i, q_i) == 1
+fn pairing_product_is_identity(terms: &[(&G1Affine, &G2Prepared)]) -> Result<(), Error> {
+    if bool::from(multi_miller_loop(terms).final_exponentiation().is_identity()) {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<Scalar>, G2Affine) {
+    assert_nonempty(params.n);
+    let sk = sample_bls12_381_lambda_with_rng(params.n, rng);
+    let pk = public_key_inner(params.hash_version, &sk);
+    (sk, pk)
+}
+
+fn expand_inner(version: HashTildeVersion, sk: &[Scalar]) -> ExpandedSigningKey {
+    assert_nonempty(sk.len());
+    let n = sk.len();
+    let a_list = Zeroizing::new(hash_tilde_bls12_381(&bls12_381_scalar_list_to_bytes(sk, version), n, false, version));
+    ExpandedSigningKey { x: aggregate_bls12_381(&a_list, sk) }
+}
+
+fn public_key_inner(version: HashTildeVersion, sk: &[Scalar]) -> G2Affine {
+    (G2Affine::generator() * expand_inner(version, sk).x).to_affine()
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey(pub(crate) SecretVec<Scalar>);
+
+impl SigningKey {
+    pub fn as_scalars(&self) -> &[Scalar] {
+        &self.0
+    }
+}
+
+impl From<Vec<Scalar>> for SigningKey {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, scalar381_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secret `<a, sk>`.
+#[derive(Clone)]
+pub struct ExpandedSigningKey {
+    x: Scalar,
+}
+
+impl std::fmt::Debug for ExpandedSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ExpandedSigningKey(..)")
+    }
+}
+
+impl Drop for ExpandedSigningKey {
+    fn drop(&mut self) {
+        self.x.zeroize();
+    }
+}
+
+/// A public key whose proof of possession has been checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifiedPublicKey(G2Affine);
+
+impl VerifiedPublicKey {
+    pub fn new(pk: &G2Affine, proof: &G1Affine) -> Result<Self, Error> {
+        try_verify_possession(pk, proof)?;
+        Ok(VerifiedPublicKey(*pk))
+    }
+
+    pub fn public_key(&self) -> &G2Affine {
+        &self.0
+    }
+}
+
+/// The sum of public keys with verified proofs of possession, against which
+/// an aggregate of signatures on a single message is checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggregatePublicKey(G2Affine);
+
+impl AggregatePublicKey {
+    pub fn new(keys: &[VerifiedPublicKey]) -> Self {
+        AggregatePublicKey(keys.iter().fold(G2Projective::identity(), |acc, pk| acc + pk.0).to_affine())
+    }
+
+    pub fn public_key(&self) -> &G2Affine {
+        &self.0
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &G1Affine) -> bool {
+        self.try_verify(msg, signature).is_ok()
+    }
+
+    /// Checks a multisignature made under `POP_SCHEME_DST`.
+    pub fn try_verify(&self, msg: &[u8], signature: &G1Affine) -> Result<(), Error> {
+        self.try_verify_with_dst(msg, signature, POP_SCHEME_DST)
+    }
+
+    pub fn try_verify_with_dst(&self, msg: &[u8], signature: &G1Affine, dst: &[u8]) -> Result<(), Error> {
+        try_verify_with_dst(&self.0, msg, signature, dst)
+    }
+}
+
+/// Compressed G2 point, 96 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(pub G2Affine);
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 96;
+
+    /// Rejects the identity and points outside the prime-order subgroup.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_g2_public_key(&self.0)
+    }
+}
+
+/// A public key prepared for the Miller loop, for verifying many signatures
+/// under the same key. The key is validated once, when it is prepared.
+#[derive(Clone, Debug)]
+pub struct PreparedVerifyingKey {
+    pk: G2Prepared,
+    valid: Result<(), Error>,
+}
+
+impl PreparedVerifyingKey {
+    pub fn new(pk: &G2Affine) -> Self {
+        PreparedVerifyingKey { pk: G2Prepared::from(*pk), valid: validate_g2_public_key(pk) }
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &G1Affine) -> bool {
+        self.try_verify(msg, signature).is_ok()
+    }
+
+    pub fn try_verify(&self, msg: &[u8], signature: &G1Affine) -> Result<(), Error> {
+        self.try_verify_with_dst(msg, signature, DEFAULT_DST)
+    }
+
+    pub fn try_verify_with_dst(&self, msg: &[u8], signature: &G1Affine, dst: &[u8]) -> Result<(), Error> {
+        self.valid?;
+        validate_g1_signature(signature)?;
+        verify_hashed(&self.pk, &hash_g1_bls12_381_with_domain(msg, dst), signature)
+    }
+}
+
+impl From<VerifyingKey> for PreparedVerifyingKey {
+    fn from(pk: VerifyingKey) -> Self {
+        PreparedVerifyingKey::new(&pk.0)
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_compressed().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(g2_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// Compressed G1 point, 48 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature(pub G1Affine);
+
+impl Signature {
+    pub const BYTE_LEN: usize = 48;
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_compressed().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let signature = g1_from_bytes(bytes)?;
+        validate_g1_signature(&signature)?;
+        Ok(Signature(signature))
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+
+pub struct Bls;
+
+impl SignatureScheme for Bls {
+    const NAME: &'static str = "LR-BLS";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    // LR-BLS signing is deterministic, so the RNG is never drawn from.
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, _pk: &VerifyingKey, msg: &[u8], _rng: &mut R) -> Result<Signature, Error> {
+        Ok(Signature(sign_expanded_with_dst(esk, msg, DEFAULT_DST)))
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &signature.0)
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     #[test]
     fn test_lr_bls() {
         let n = 100;
         let msg = "Hello, world!";
         let (sk, pk) = keygen(n);
-        let signature = sign(&sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let signature = sign(&sk, msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature))
+    }
+
+    #[test]
+    fn test_lr_bls_prepared_verify() {
+        let n = 20;
+        let msg = "Hello, world!".as_bytes();
+        let (sk, pk) = keygen(n);
+        let prepared = PreparedVerifyingKey::new(&pk);
+        assert!(prepared.verify(msg, &sign(&sk, msg)));
+        assert!(!prepared.verify("Goodbye, world!".as_bytes(), &sign(&sk, msg)));
+        assert!(prepared.try_verify_with_dst(msg, &sign_with_dst(&sk, msg, b"custom"), b"custom").is_ok());
+        assert!(!prepared.verify(msg, &sign_with_dst(&sk, msg, b"custom")));
+    }
+
+    #[test]
+    fn test_lr_bls_aggregate() {
+        let n = 20;
+        let messages: Vec<Vec<u8>> = (0..5).map(|i| format!("block {}", i).into_bytes()).collect();
+        let keys: Vec<(Vec<Scalar>, G2Affine)> = messages.iter().map(|_| keygen(n)).collect();
+        let signatures: Vec<G1Affine> = keys.iter().zip(&messages).map(|((sk, _), msg)| sign(sk, msg)).collect();
+        let mut entries: Vec<(G2Affine, &[u8])> = keys.iter().zip(&messages).map(|((_, pk), msg)| (*pk, msg.as_slice())).collect();
+
+        let signature = aggregate(&signatures);
+        assert!(aggregate_verify(&entries, &signature));
+        assert!(!aggregate_verify(&entries, &aggregate(&signatures[1..])));
+        assert!(!aggregate_verify(&entries[1..], &signature));
+        assert!(!aggregate_verify(&[], &aggregate(&[])));
+        entries[3].1 = b"forged";
+        assert!(!aggregate_verify(&entries, &signature));
+    }
+
+    #[test]
+    fn test_lr_bls_aggregate_duplicate_message_rejected() {
+        let msg = "Hello, world!".as_bytes();
+        let keys: Vec<(Vec<Scalar>, G2Affine)> = (0..2).map(|_| keygen(20)).collect();
+        let signature = aggregate(&keys.iter().map(|(sk, _)| sign(sk, msg)).collect::<Vec<_>>());
+        let entries: Vec<(G2Affine, &[u8])> = keys.iter().map(|(_, pk)| (*pk, msg)).collect();
+        assert_eq!(try_aggregate_verify(&entries, &signature), Err(Error::DuplicateMessage));
+    }
+
+    fn verified_keygen(n: usize) -> (Vec<Scalar>, VerifiedPublicKey) {
+        let (sk, pk) = keygen(n);
+        let verified = VerifiedPublicKey::new(&pk, &prove_possession(&sk)).unwrap();
+        (sk, verified)
+    }
+
+    #[test]
+    fn test_lr_bls_fast_aggregate() {
+        let n = 20;
+        let msg = "Hello, world!".as_bytes();
+        let keys: Vec<(Vec<Scalar>, VerifiedPublicKey)> = (0..5).map(|_| verified_keygen(n)).collect();
+        let pks: Vec<VerifiedPublicKey> = keys.iter().map(|(_, pk)| *pk).collect();
+        let signature = aggregate(&keys.iter().map(|(sk, _)| sign_with_dst(sk, msg, POP_SCHEME_DST)).collect::<Vec<_>>());
+
+        assert!(fast_aggregate_verify(&pks, msg, &signature));
+        assert!(!fast_aggregate_verify(&pks, msg, &aggregate(&keys.iter().map(|(sk, _)| sign(sk, msg)).collect::<Vec<_>>())));
+        assert!(!fast_aggregate_verify(&pks[1..], msg, &signature));
+        assert!(!fast_aggregate_verify(&pks, "Goodbye, world!".as_bytes(), &signature));
+        assert!(!fast_aggregate_verify(&[], msg, &signature));
+    }
+
+    #[test]
+    fn test_lr_bls_proof_of_possession() {
+        let n = 20;
+        let (sk, pk) = keygen(n);
+        let (_, other_pk) = keygen(n);
+        let proof = prove_possession(&sk);
+        assert!(verify_possession(&pk, &proof));
+        assert!(!verify_possession(&other_pk, &proof));
+        assert!(!verify(&pk, &pk.to_compressed(), &proof));
+        assert!(!verify_possession(&pk, &sign(&sk, &pk.to_compressed())));
+        assert_eq!(try_verify_possession(&G2Affine::identity(), &G1Affine::identity()), Err(Error::IdentityPublicKey));
+    }
+
+    #[test]
+    fn test_lr_bls_aggregate_public_key() {
+        let n = 20;
+        let msg = "Hello, world!".as_bytes();
+        let keys: Vec<(Vec<Scalar>, G2Affine)> = (0..5).map(|_| keygen(n)).collect();
+        let verified: Vec<VerifiedPublicKey> = keys.iter().map(|(sk, pk)| VerifiedPublicKey::new(pk, &prove_possession(sk)).unwrap()).collect();
+        let signature = aggregate(&keys.iter().map(|(sk, _)| sign_with_dst(sk, msg, POP_SCHEME_DST)).collect::<Vec<_>>());
+        assert!(AggregatePublicKey::new(&verified).verify(msg, &signature));
+        assert!(!AggregatePublicKey::new(&verified[1..]).verify(msg, &signature));
+        assert_eq!(AggregatePublicKey::new(&[]).try_verify(msg, &signature), Err(Error::IdentityPublicKey));
+    }
+
+    #[test]
+    fn test_lr_bls_rogue_key_rejected() {
+        let msg = "Hello, world!".as_bytes();
+        let (_, honest_pk) = verified_keygen(20);
+        // The attacker publishes g2^y - honest_pk and signs alone for both keys.
+        let y = Scalar::from(42u64);
+        let rogue_pk = (G2Affine::generator() * y - G2Projective::from(*honest_pk.public_key())).to_affine();
+        let forged = (hash_g1_bls12_381_with_domain(msg, POP_SCHEME_DST) * y).to_affine();
+        let proof = (hash_g1_bls12_381_with_domain(&rogue_pk.to_compressed(), POP_DOMAIN) * y).to_affine();
+        assert_eq!(VerifiedPublicKey::new(&rogue_pk, &proof), Err(Error::SignatureInvalid));
+
+        // The forgery verifies under the sum of both keys, but the rogue key
+        // never gets into a set of verified keys.
+        assert!(verify_with_dst(&(G2Affine::generator() * y).to_affine(), msg, &forged, POP_SCHEME_DST));
+        assert!(!fast_aggregate_verify(&[honest_pk], msg, &forged));
+        assert!(!AggregatePublicKey::new(&[honest_pk]).verify(msg, &forged));
+    }
+
+    #[test]
+    fn test_lr_bls_custom_dst() {
+        let n = 20;
+        let msg = "Hello, world!".as_bytes();
+        let dst = b"MY-APP-V1-WITH-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let (sk, pk) = keygen(n);
+        let signature = sign_with_dst(&sk, msg, dst);
+        assert!(verify_with_dst(&pk, msg, &signature, dst));
+        assert!(!verify(&pk, msg, &signature));
+        assert!(!verify_with_dst(&pk, msg, &sign(&sk, msg), dst));
+        assert_eq!(try_aggregate_verify_with_dst(&[(pk, msg)], &signature, dst), Ok(()));
+        let verified = VerifiedPublicKey::new(&pk, &prove_possession(&sk)).unwrap();
+        assert_eq!(try_fast_aggregate_verify_with_dst(&[verified], msg, &signature, dst), Ok(()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lr_bls_legacy_signature() {
+        let n = 20;
+        let msg = "Hello, world!".as_bytes();
+        let (sk, pk) = keygen(n);
+        let esk = Bls::expand(&Params::new(n), &SigningKey::from(sk));
+        let legacy = (encode_g1_bls12_381_legacy(msg) * esk.x).to_affine();
+        assert_eq!(try_verify_legacy(&pk, msg, &legacy), Ok(()));
+        assert!(!verify(&pk, msg, &legacy));
+    }
+
+    // A point on the G2 curve outside the prime-order subgroup, decompressed
+    // without the subgroup check.
+    pub(crate) fn g2_outside_subgroup() -> G2Affine {
+        (1u8..=255).find_map(|i| {
+            let mut bytes = [0u8; 96];
+            bytes[0] = 0x80;
+            bytes[95] = i;
+            Option::<G2Affine>::from(G2Affine::from_compressed_unchecked(&bytes)).filter(|point| !bool::from(point.is_torsion_free()))
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_lr_bls_rejects_invalid_points() {
+        let mut identity = [0u8; 96];
+        identity[0] = 0xc0;
+        assert_eq!(VerifyingKey::from_bytes(&identity), Err(Error::IdentityPublicKey));
+        assert_eq!(Signature::from_bytes(&G1Affine::identity().to_compressed()), Err(Error::SignatureInvalid));
+
+        let point = g2_outside_subgroup();
+        assert!(bool::from(point.is_on_curve()));
+        assert_eq!(VerifyingKey(point).validate(), Err(Error::MalformedPoint));
+        assert_eq!(VerifyingKey::from_bytes(&point.to_compressed()), Err(Error::MalformedPoint));
+
+        let msg = b"Hello, world!";
+        let (sk, pk) = keygen(10);
+        let signature = sign(&sk, msg);
+        assert_eq!(try_verify(&pk, msg, &signature), Ok(()));
+        assert_eq!(try_verify(&point, msg, &signature), Err(Error::MalformedPoint));
+        assert_eq!(PreparedVerifyingKey::new(&point).try_verify(msg, &signature), Err(Error::MalformedPoint));
+        assert_eq!(VerifiedPublicKey::new(&point, &prove_possession(&sk)), Err(Error::MalformedPoint));
+        assert_eq!(try_verify(&pk, msg, &G1Affine::identity()), Err(Error::SignatureInvalid));
+    }
+}
@@ -1,35 +1,213 @@
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
-use sha2::Sha512;
+use rand::{CryptoRng, RngCore, thread_rng};
 
-use super::{aggregate_curve25519, sample_curve25519_lambda, hash_tilde_curve25519, curve25519_scalar_list_to_bytes};
+use super::encoding::{check_length, scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar25519_from_bytes, ristretto_from_bytes};
+use super::secret::SecretVec;
+use super::generic::{self, Ristretto, RistrettoScalar, from_ristretto_scalars, to_ristretto_scalars};
+use super::{ByteEncoding, SecretEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, retry_degenerate, validate_canonical, validate_nonempty, validate_ristretto_public_key};
 
 pub fn keygen(n: usize) -> (Vec<Scalar>, RistrettoPoint) {
-    let sk = sample_curve25519_lambda(n);
-    let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk), n, false);
-    let pk = RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&a_list, &sk);
-    (sk, pk)
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    keygen_inner(&Params::new(n), rng)
 }
 
 pub fn sign(sk: &[Scalar], msg: &[u8]) -> (Scalar, Scalar) {
-    let n = sk.len();
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
-    let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk), n, false);
-    let k_list = sample_curve25519_lambda(n);
-    let b_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&k_list), n, true);
-    let point = RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&b_list, &k_list);
-    let r = Scalar::from_bytes_mod_order(point.compress().to_bytes());
-    let s = aggregate_curve25519(&b_list, &k_list).invert();
-    let s = s * (msg_hash + r * aggregate_curve25519(&a_list, &sk));
-    (r, s)
+    sign_with_rng(sk, msg, &mut thread_rng())
+}
+
+/// Panics if `sk` is empty.
+pub fn sign_with_rng<R: RngCore + CryptoRng>(sk: &[Scalar], msg: &[u8], rng: &mut R) -> (Scalar, Scalar) {
+    retry_degenerate(Ecdsa::NAME, || try_sign_with_rng(sk, msg, rng))
+}
+
+pub fn try_sign_with_rng<R: RngCore + CryptoRng>(sk: &[Scalar], msg: &[u8], rng: &mut R) -> Result<(Scalar, Scalar), Error> {
+    validate_nonempty(sk)?;
+    sign_inner(&expand_inner(HashTildeVersion::default(), sk), msg, rng)
 }
 
 pub fn verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar)) -> Result<(), Error> {
     let (r, s) = signature;
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
-    let point = (RISTRETTO_BASEPOINT_POINT * msg_hash + pk * r) * s.invert();
-    r == &Scalar::from_bytes_mod_order(point.compress().to_bytes())
+    validate_ristretto_public_key(pk)?;
+    validate_canonical(&[r, s])?;
+    validate_nonzero(r, s)?;
+    generic::ecdsa::try_verify(&Ristretto(*pk), msg, &(RistrettoScalar(*r), RistrettoScalar(*s)))
+}
+
+// `r = s = 0` would otherwise verify under every key: `0^-1 = 0`, and the
+// identity encodes as zero.
+fn validate_nonzero(r: &Scalar, s: &Scalar) -> Result<(), Error> {
+    if r == &Scalar::zero() || s == &Scalar::zero() {
+        return Err(Error::ZeroScalar);
+    }
+    Ok(())
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    let (sk, pk) = generic::ecdsa::keygen_inner::<Ristretto, R>(params, rng);
+    (from_ristretto_scalars(sk), pk.0)
+}
+
+fn expand_inner(version: HashTildeVersion, sk: &[Scalar]) -> ExpandedSigningKey {
+    generic::ecdsa::expand_inner(version, to_ristretto_scalars(sk))
+}
+
+fn public_key_inner(version: HashTildeVersion, sk: &[Scalar]) -> RistrettoPoint {
+    generic::ecdsa::public_key_inner::<Ristretto>(version, to_ristretto_scalars(sk)).0
+}
+
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, msg: &[u8], rng: &mut R) -> Result<(Scalar, Scalar), Error> {
+    let (r, s) = generic::ecdsa::sign_inner(esk, msg, rng)?;
+    Ok((r.0, s.0))
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey(pub(crate) SecretVec<Scalar>);
+
+impl SigningKey {
+    pub fn as_scalars(&self) -> &[Scalar] {
+        &self.0
+    }
+}
+
+impl From<Vec<Scalar>> for SigningKey {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, scalar25519_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secret `<a, sk>` of `generic::ecdsa`.
+pub type ExpandedSigningKey = generic::ecdsa::ExpandedSigningKey<Ristretto>;
+
+/// Compressed Ristretto point, 32 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(pub RistrettoPoint);
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 32;
+
+    /// Rejects the identity. Every Ristretto point lies in the prime-order
+    /// group, so no subgroup check is needed.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_ristretto_public_key(&self.0)
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.compress().to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(ristretto_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `r || s`, two canonical nonzero scalars, 64 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub r: Scalar,
+    pub s: Scalar,
+}
+
+impl Signature {
+    pub const BYTE_LEN: usize = 64;
+}
+
+impl From<(Scalar, Scalar)> for Signature {
+    fn from((r, s): (Scalar, Scalar)) -> Self {
+        Signature { r, s }
+    }
+}
+
+impl From<Signature> for (Scalar, Scalar) {
+    fn from(signature: Signature) -> Self {
+        (signature.r, signature.s)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.r.as_bytes());
+        bytes.extend_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        let (r, s) = (scalar25519_from_bytes(&bytes[0..32])?, scalar25519_from_bytes(&bytes[32..64])?);
+        validate_nonzero(&r, &s)?;
+        Ok(Signature { r, s })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+
+pub struct Ecdsa;
+
+impl SignatureScheme for Ecdsa {
+    const NAME: &'static str = "LR-ECDSA";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, _pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        sign_inner(esk, msg, rng).map(Signature::from)
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
 }
 
 #[cfg(test)]
@@ -40,7 +218,98 @@ mod tests {
         let n = 100;
         let msg = "Hello, world!";
         let (sk, pk) = keygen(n);
-        let signature = sign(&sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let signature = sign(&sk, msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature))
     }
-}
\ No newline at end of file
+
+    struct ZeroRng;
+
+    impl RngCore for ZeroRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.iter_mut().for_each(|b| *b = 0);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for ZeroRng {}
+
+    #[test]
+    fn test_lr_ecdsa_degenerate_nonce() {
+        let (sk, _) = keygen(1);
+        assert_eq!(try_sign_with_rng(&sk, b"Hello, world!", &mut ZeroRng), Err(Error::DegenerateNonce));
+    }
+
+    #[test]
+    #[should_panic(expected = "degenerate nonces in a row")]
+    fn test_lr_ecdsa_gives_up_on_broken_rng() {
+        let (sk, _) = keygen(1);
+        sign_with_rng(&sk, b"Hello, world!", &mut ZeroRng);
+    }
+
+    #[test]
+    fn test_lr_ecdsa_hardened_gives_up_on_broken_rng() {
+        let params = Params::new(1);
+        let (sk, pk) = Ecdsa::keygen(&params);
+        assert_eq!(Ecdsa::sign_hardened_with_rng(&params, &sk, &pk, b"Hello, world!", &mut ZeroRng), Err(Error::DegenerateNonce));
+    }
+
+    #[test]
+    fn test_lr_ecdsa_rejects_empty_key() {
+        let msg = b"Hello, world!";
+        assert_eq!(try_sign_with_rng(&[], msg, &mut thread_rng()), Err(Error::EmptySecretKey));
+        assert_eq!(SigningKey::from_bytes(&0u64.to_le_bytes()).err(), Some(Error::EmptySecretKey));
+        assert!(std::panic::catch_unwind(|| SigningKey::from(Vec::new())).is_err());
+        assert!(std::panic::catch_unwind(|| Ecdsa::keygen(&Params::new(0))).is_err());
+        assert!(std::panic::catch_unwind(|| sign(&[], msg)).is_err());
+    }
+
+    #[test]
+    fn test_lr_ecdsa_rejects_zero_scalars() {
+        let msg = b"Hello, world!";
+        let (_, pk) = keygen(10);
+        // With r = s = 0 the check would compute 0^-1 = 0 and accept.
+        assert_eq!(try_verify(&pk, msg, &(Scalar::zero(), Scalar::zero())), Err(Error::ZeroScalar));
+        assert_eq!(try_verify(&pk, msg, &(Scalar::one(), Scalar::zero())), Err(Error::ZeroScalar));
+        assert_eq!(Signature::from_bytes(&[0u8; 64]), Err(Error::ZeroScalar));
+        assert_eq!(try_verify(&RistrettoPoint::default(), msg, &(Scalar::one(), Scalar::one())), Err(Error::IdentityPublicKey));
+        assert_eq!(VerifyingKey::from_bytes(&[0u8; 32]), Err(Error::IdentityPublicKey));
+    }
+
+    #[test]
+    fn test_lr_ecdsa_rejects_malformed_encodings() {
+        let mut bytes = [0u8; 32];
+        bytes[31] = 0x80;
+        assert_eq!(Signature::from_bytes(&[bytes, bytes].concat()).err(), Some(Error::NonCanonicalScalar));
+        assert_eq!(VerifyingKey::from_bytes(&[0xff; 32]).err(), Some(Error::MalformedPoint));
+    }
+
+    #[test]
+    fn test_lr_ecdsa_deterministic() {
+        let params = Params::new(20);
+        let sk: Vec<Scalar> = (1..=20u64).map(Scalar::from).collect();
+        let sk = SigningKey::from(sk);
+        let pk = Ecdsa::public_key(&params, &sk);
+        let msg = b"Hello, world!";
+        let signature = Ecdsa::sign_deterministic(&params, &sk, &pk, msg);
+        assert!(Ecdsa::verify(&params, &pk, msg, &signature));
+        assert_eq!(Ecdsa::sign_deterministic(&params, &sk, &pk, msg), signature);
+        let hex: String = signature.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "a97bef47e31c1d983a3792b5aca3a875c1103e9053a70bc264e7c11ef85d6b0b32527acde2bd456267d7958d047652e1b3f4f35eac44a6b08922030797cbbb0f");
+
+        let hedged = Ecdsa::sign_hedged(&params, &sk, &pk, msg);
+        assert!(Ecdsa::verify(&params, &pk, msg, &hedged));
+        assert_ne!(hedged, signature);
+    }
+}
@@ -0,0 +1,144 @@
+//! LR-ECDSA over P-256, with the `p256` ECDSA types. See
+//! `ecdsa_weierstrass` for the implementation.
+
+use p256::{ProjectivePoint, PublicKey, Scalar};
+use p256::ecdsa::{Signature as EcdsaSignature, VerifyingKey as EcdsaVerifyingKey};
+use p256::ecdsa::signature::Verifier;
+use rand::{CryptoRng, RngCore, thread_rng};
+use std::convert::TryFrom;
+
+use super::ecdsa_weierstrass::{self, EcdsaWeierstrass, WeierstrassCurve};
+use super::Error;
+
+pub type SigningKey = ecdsa_weierstrass::SigningKey<ProjectivePoint>;
+pub type VerifyingKey = ecdsa_weierstrass::VerifyingKey<ProjectivePoint>;
+pub type Signature = ecdsa_weierstrass::Signature<ProjectivePoint>;
+pub type ExpandedSigningKey = crate::generic::ecdsa::ExpandedSigningKey<ProjectivePoint>;
+pub type EcdsaP256 = EcdsaWeierstrass<ProjectivePoint>;
+
+/// Panics if `n == 0`.
+pub fn keygen(n: usize) -> (Vec<Scalar>, EcdsaVerifyingKey) {
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<Scalar>, EcdsaVerifyingKey) {
+    ecdsa_weierstrass::keygen_with_rng::<ProjectivePoint, R>(n, rng)
+}
+
+pub fn sign(sk: &[Scalar], msg: &[u8]) -> EcdsaSignature {
+    sign_with_rng(sk, msg, &mut thread_rng())
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(sk: &[Scalar], msg: &[u8], rng: &mut R) -> EcdsaSignature {
+    ecdsa_weierstrass::sign_with_rng::<ProjectivePoint, R>(sk, msg, rng)
+}
+
+pub fn try_sign_with_rng<R: RngCore + CryptoRng>(sk: &[Scalar], msg: &[u8], rng: &mut R) -> Result<EcdsaSignature, Error> {
+    ecdsa_weierstrass::try_sign_with_rng::<ProjectivePoint, R>(sk, msg, rng)
+}
+
+pub fn verify(pk: &EcdsaVerifyingKey, msg: &[u8], signature: &EcdsaSignature) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+/// Standard ECDSA-SHA256 verification.
+pub fn try_verify(pk: &EcdsaVerifyingKey, msg: &[u8], signature: &EcdsaSignature) -> Result<(), Error> {
+    pk.verify(msg, signature).map_err(|_| Error::SignatureInvalid)
+}
+
+impl WeierstrassCurve for ProjectivePoint {
+    const SCHEME_NAME: &'static str = "LR-ECDSA-P256";
+
+    type EcdsaVerifyingKey = EcdsaVerifyingKey;
+    type EcdsaSignature = EcdsaSignature;
+
+    fn verifying_key(point: &Self) -> Result<EcdsaVerifyingKey, Error> {
+        PublicKey::from_affine(point.to_affine()).map(|pk| EcdsaVerifyingKey::from(&pk)).map_err(|_| Error::IdentityPublicKey)
+    }
+
+    fn verifying_key_to_bytes(pk: &EcdsaVerifyingKey) -> Vec<u8> {
+        pk.to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    fn verifying_key_from_bytes(bytes: &[u8]) -> Result<EcdsaVerifyingKey, Error> {
+        EcdsaVerifyingKey::from_sec1_bytes(bytes).map_err(|_| Error::MalformedPoint)
+    }
+
+    fn signature(r: Scalar, s: Scalar) -> Result<EcdsaSignature, Error> {
+        EcdsaSignature::from_scalars(r, s).map_err(|_| Error::ZeroScalar)
+    }
+
+    fn signature_from_bytes(bytes: &[u8]) -> Result<EcdsaSignature, Error> {
+        EcdsaSignature::try_from(bytes).map_err(|_| Error::NonCanonicalScalar)
+    }
+
+    fn verify(pk: &EcdsaVerifyingKey, msg: &[u8], signature: &EcdsaSignature) -> Result<(), Error> {
+        try_verify(pk, msg, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ByteEncoding, Params, SignatureScheme};
+    use ff::Field;
+
+    #[test]
+    fn test_lr_ecdsa_p256() {
+        let n = 100;
+        let msg = "Hello, world!";
+        let (sk, pk) = keygen(n);
+        let signature = sign(&sk, msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature));
+        assert!(!verify(&pk, "Goodbye, world!".as_bytes(), &signature));
+    }
+
+    #[test]
+    fn test_lr_ecdsa_p256_standard_verifier() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = EcdsaP256::keygen(&params);
+        let esk = EcdsaP256::expand(&params, &sk);
+        let signature = EcdsaP256::sign_expanded(&esk, &pk, msg);
+
+        // The aggregated secret is an ordinary P-256 key.
+        let standard_sk = p256::ecdsa::SigningKey::from_bytes(&esk.x.to_bytes()).unwrap();
+        assert_eq!(standard_sk.verifying_key(), pk.0);
+
+        let standard_pk = EcdsaVerifyingKey::from_sec1_bytes(&pk.to_bytes()).unwrap();
+        let der = signature.0.to_der();
+        let standard_signature = EcdsaSignature::from_der(der.as_bytes()).unwrap();
+        assert!(standard_pk.verify(msg, &standard_signature).is_ok());
+
+        let standard_signature: EcdsaSignature = p256::ecdsa::signature::Signer::sign(&standard_sk, msg);
+        assert!(verify(&pk.0, msg, &standard_signature));
+        assert_eq!(Signature::from_bytes(&signature.to_bytes()), Ok(signature));
+    }
+
+    #[test]
+    fn test_lr_ecdsa_p256_deterministic() {
+        let params = Params::new(20);
+        let sk: Vec<Scalar> = (1..=20u64).map(Scalar::from).collect();
+        let sk = SigningKey::try_from(sk).unwrap();
+        let pk = EcdsaP256::public_key(&params, &sk);
+        let msg = b"Hello, world!";
+        let signature = EcdsaP256::sign_deterministic(&params, &sk, &pk, msg);
+        assert!(EcdsaP256::verify(&params, &pk, msg, &signature));
+        assert_eq!(EcdsaP256::sign_deterministic(&params, &sk, &pk, msg), signature);
+        let hex: String = signature.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "294778fc9cafd08c56ce878981ed8e3a7b04ec8af2ce1b08f314af58d5c91424de024d44600c3f21d9c965569d5dd064844de45606f28cab4ad4725a5b5c449d");
+
+        let hedged = EcdsaP256::sign_hedged(&params, &sk, &pk, msg);
+        assert!(EcdsaP256::verify(&params, &pk, msg, &hedged));
+        assert_ne!(hedged, signature);
+    }
+
+    #[test]
+    fn test_lr_ecdsa_p256_rejects_zero_aggregate() {
+        let zero = vec![Scalar::zero(); 20];
+        assert_eq!(SigningKey::try_from(zero.clone()).err(), Some(Error::ZeroAggregate));
+        let bytes = [&20u64.to_le_bytes()[..], &[0u8; 32 * 20]].concat();
+        assert_eq!(SigningKey::from_bytes(&bytes).err(), Some(Error::ZeroAggregate));
+        assert!(std::panic::catch_unwind(|| keygen(0)).is_err());
+    }
+}
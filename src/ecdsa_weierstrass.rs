@@ -0,0 +1,199 @@
+//! LR-ECDSA with the standard ECDSA types of a short Weierstrass curve, the
+//! implementation behind `ecdsa_secp256k1` and `ecdsa_p256`.
+//!
+//! The arithmetic is that of `generic::ecdsa`, which over these curves is
+//! ECDSA-SHA256. A `WeierstrassCurve` only supplies the conversions to and
+//! from the ECDSA types of its crate, so that any standard verifier accepts
+//! the signatures.
+
+use ff::{Field, PrimeField};
+use rand::{CryptoRng, RngCore};
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+
+use crate::encoding::{check_length, prime_field_from_bytes, scalar_vectors_from_bytes, scalar_vectors_to_bytes};
+use crate::generic::LrGroup;
+use crate::generic::ecdsa::{ExpandedSigningKey, expand_inner, keygen_inner, public_key_inner, sign_inner};
+use crate::secret::SecretVec;
+use crate::{ByteEncoding, SecretEncoding, Error, Params, SignatureScheme, HashTildeVersion, retry_degenerate, validate_nonempty};
+
+/// A curve with a standard ECDSA implementation to interoperate with.
+pub trait WeierstrassCurve: LrGroup {
+    /// `SignatureScheme::NAME` of LR-ECDSA over the curve.
+    const SCHEME_NAME: &'static str;
+
+    type EcdsaVerifyingKey: Copy + std::fmt::Debug + Eq;
+    type EcdsaSignature: Copy + std::fmt::Debug + Eq + AsRef<[u8]>;
+
+    /// Fails with `Error::IdentityPublicKey` on the identity.
+    fn verifying_key(point: &Self) -> Result<Self::EcdsaVerifyingKey, Error>;
+    /// The SEC 1 compressed encoding.
+    fn verifying_key_to_bytes(pk: &Self::EcdsaVerifyingKey) -> Vec<u8>;
+    fn verifying_key_from_bytes(bytes: &[u8]) -> Result<Self::EcdsaVerifyingKey, Error>;
+    fn signature(r: Self::Scalar, s: Self::Scalar) -> Result<Self::EcdsaSignature, Error>;
+    fn signature_from_bytes(bytes: &[u8]) -> Result<Self::EcdsaSignature, Error>;
+    /// Standard ECDSA-SHA256 verification.
+    fn verify(pk: &Self::EcdsaVerifyingKey, msg: &[u8], signature: &Self::EcdsaSignature) -> Result<(), Error>;
+}
+
+/// Panics if `n == 0`.
+pub fn keygen_with_rng<C: WeierstrassCurve, R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<C::Scalar>, C::EcdsaVerifyingKey) {
+    let (sk, pk) = keygen_inner::<C, R>(&Params::new(n), rng);
+    (sk, C::verifying_key(&pk).expect("keygen never returns a zero aggregate"))
+}
+
+/// Panics if `sk` is empty.
+pub fn sign_with_rng<C: WeierstrassCurve, R: RngCore + CryptoRng>(sk: &[C::Scalar], msg: &[u8], rng: &mut R) -> C::EcdsaSignature {
+    retry_degenerate(C::SCHEME_NAME, || try_sign_with_rng::<C, R>(sk, msg, rng))
+}
+
+pub fn try_sign_with_rng<C: WeierstrassCurve, R: RngCore + CryptoRng>(sk: &[C::Scalar], msg: &[u8], rng: &mut R) -> Result<C::EcdsaSignature, Error> {
+    validate_nonempty(sk)?;
+    try_sign_expanded_with_rng(&expand_inner::<C>(HashTildeVersion::default(), sk), msg, rng)
+}
+
+fn try_sign_expanded_with_rng<C: WeierstrassCurve, R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<C>, msg: &[u8], rng: &mut R) -> Result<C::EcdsaSignature, Error> {
+    let (r, s) = sign_inner(esk, msg, rng)?;
+    C::signature(r, s).map_err(|_| Error::DegenerateNonce)
+}
+
+// Every version is checked, as the key does not record the one it is used
+// with. A vector that is zero under any of them is as rare as a zero key.
+fn validate_aggregate<C: WeierstrassCurve>(sk: &[C::Scalar]) -> Result<(), Error> {
+    for &version in HashTildeVersion::ALL.iter() {
+        if bool::from(expand_inner::<C>(version, sk).x.is_zero()) {
+            return Err(Error::ZeroAggregate);
+        }
+    }
+    Ok(())
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte big-endian
+/// scalars. It never aggregates to zero, which has no public key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey<C: WeierstrassCurve>(pub(crate) SecretVec<C::Scalar>);
+
+impl<C: WeierstrassCurve> SigningKey<C> {
+    pub fn as_scalars(&self) -> &[C::Scalar] {
+        &self.0
+    }
+}
+
+impl<C: WeierstrassCurve> TryFrom<Vec<C::Scalar>> for SigningKey<C> {
+    type Error = Error;
+
+    /// Fails with `Error::EmptySecretKey` if `sk` is empty, and with
+    /// `Error::ZeroAggregate` if it aggregates to zero under any
+    /// `HashTildeVersion`.
+    fn try_from(sk: Vec<C::Scalar>) -> Result<Self, Error> {
+        validate_nonempty(&sk)?;
+        let sk = SigningKey(sk.into());
+        validate_aggregate::<C>(&sk.0)?;
+        Ok(sk)
+    }
+}
+
+impl<C: WeierstrassCurve> std::fmt::Debug for SigningKey<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl<C: WeierstrassCurve> ByteEncoding for SigningKey<C> {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], |s| s.to_repr())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, prime_field_from_bytes)?;
+        let sk = SigningKey(vectors.pop().unwrap().into());
+        validate_aggregate::<C>(&sk.0)?;
+        Ok(sk)
+    }
+}
+
+impl<C: WeierstrassCurve> SecretEncoding for SigningKey<C> {}
+
+/// SEC 1 compressed point, 33 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey<C: WeierstrassCurve>(pub C::EcdsaVerifyingKey);
+
+impl<C: WeierstrassCurve> VerifyingKey<C> {
+    pub const BYTE_LEN: usize = 33;
+
+    /// Always succeeds: an ECDSA verifying key is never the identity, and
+    /// its signatures have nonzero canonical `r` and `s` by construction.
+    pub fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<C: WeierstrassCurve> ByteEncoding for VerifyingKey<C> {
+    fn to_bytes(&self) -> Vec<u8> {
+        C::verifying_key_to_bytes(&self.0)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        C::verifying_key_from_bytes(bytes).map(VerifyingKey)
+    }
+}
+
+/// `r || s`, two big-endian scalars, 64 bytes. This is the fixed-size ECDSA
+/// encoding; `Signature.0.to_der()` gives the ASN.1 form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature<C: WeierstrassCurve>(pub C::EcdsaSignature);
+
+impl<C: WeierstrassCurve> Signature<C> {
+    pub const BYTE_LEN: usize = 64;
+}
+
+impl<C: WeierstrassCurve> ByteEncoding for Signature<C> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_ref().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        C::signature_from_bytes(bytes).map(Signature)
+    }
+}
+
+impl_serde_via_bytes!(SigningKey<C: WeierstrassCurve>);
+impl_serde_via_bytes!(VerifyingKey<C: WeierstrassCurve>);
+impl_serde_via_bytes!(Signature<C: WeierstrassCurve>);
+
+pub struct EcdsaWeierstrass<C>(PhantomData<C>);
+
+impl<C: WeierstrassCurve> SignatureScheme for EcdsaWeierstrass<C> {
+    const NAME: &'static str = C::SCHEME_NAME;
+
+    type Params = Params;
+    type SecretKey = SigningKey<C>;
+    type PublicKey = VerifyingKey<C>;
+    type Signature = Signature<C>;
+    type ExpandedSecretKey = ExpandedSigningKey<C>;
+
+    /// Panics if `params.n == 0`.
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey<C>, VerifyingKey<C>) {
+        let (sk, pk) = keygen_inner::<C, R>(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(C::verifying_key(&pk).expect("keygen never returns a zero aggregate")))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey<C>) -> VerifyingKey<C> {
+        let pk = public_key_inner::<C>(params.hash_version, &sk.0);
+        VerifyingKey(C::verifying_key(&pk).expect("a SigningKey never aggregates to zero"))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey<C>) -> ExpandedSigningKey<C> {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<C>, _pk: &VerifyingKey<C>, msg: &[u8], rng: &mut R) -> Result<Signature<C>, Error> {
+        try_sign_expanded_with_rng(esk, msg, rng).map(Signature)
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey<C>, msg: &[u8], signature: &Signature<C>) -> Result<(), Error> {
+        C::verify(&pk.0, msg, &signature.0)
+    }
+}
@@ -0,0 +1,224 @@
+use curve25519_dalek::scalar::Scalar as Scalar25519;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use bls12_381::{G1Affine, G2Affine};
+use bls12_381::Scalar as Scalar381;
+use ff::PrimeField;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use zeroize::Zeroizing;
+
+use super::{Error, HashTildeVersion};
+
+/// Fixed binary encoding of keys and signatures.
+///
+/// Points are stored compressed and scalars in their canonical 32-byte
+/// little-endian form, or big-endian as in SEC 1 on secp256k1 and P-256.
+/// Secret vectors are prefixed with their length `n` as a little-endian `u64`.
+/// `from_bytes` rejects anything that `to_bytes` would not have produced.
+pub trait ByteEncoding: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+/// `ByteEncoding` of secret keys and shares.
+///
+/// `to_secret_bytes` hands out the encoding in a buffer that is wiped when
+/// dropped. Secret keys allocate their encoding at its final length, so the
+/// buffer never reallocates and leaves no copy behind.
+pub trait SecretEncoding: ByteEncoding {
+    fn to_secret_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.to_bytes())
+    }
+}
+
+pub(crate) fn check_length(bytes: &[u8], expected: usize) -> Result<(), Error> {
+    if bytes.len() != expected {
+        return Err(Error::LengthMismatch { expected, actual: bytes.len() });
+    }
+    Ok(())
+}
+
+fn fixed<const N: usize>(bytes: &[u8]) -> Result<[u8; N], Error> {
+    check_length(bytes, N)?;
+    Ok(bytes.try_into().unwrap())
+}
+
+pub(crate) fn scalar25519_from_bytes(bytes: &[u8]) -> Result<Scalar25519, Error> {
+    Scalar25519::from_canonical_bytes(fixed(bytes)?).ok_or(Error::NonCanonicalScalar)
+}
+
+pub(crate) fn ristretto_from_bytes(bytes: &[u8]) -> Result<RistrettoPoint, Error> {
+    check_length(bytes, 32)?;
+    CompressedRistretto::from_slice(bytes).decompress().ok_or(Error::MalformedPoint)
+}
+
+pub(crate) fn scalar381_from_bytes(bytes: &[u8]) -> Result<Scalar381, Error> {
+    Option::from(Scalar381::from_bytes(&fixed(bytes)?)).ok_or(Error::NonCanonicalScalar)
+}
+
+pub(crate) fn prime_field_from_bytes<F: PrimeField>(bytes: &[u8]) -> Result<F, Error> {
+    let mut repr = F::Repr::default();
+    check_length(bytes, repr.as_ref().len())?;
+    repr.as_mut().copy_from_slice(bytes);
+    Option::from(F::from_repr(repr)).ok_or(Error::NonCanonicalScalar)
+}
+
+pub(crate) fn g1_from_bytes(bytes: &[u8]) -> Result<G1Affine, Error> {
+    Option::from(G1Affine::from_compressed(&fixed(bytes)?)).ok_or(Error::MalformedPoint)
+}
+
+pub(crate) fn g2_from_bytes(bytes: &[u8]) -> Result<G2Affine, Error> {
+    Option::from(G2Affine::from_compressed(&fixed(bytes)?)).ok_or(Error::MalformedPoint)
+}
+
+/// Writes `n` followed by every vector in `vectors`, each of which has length `n`.
+pub(crate) fn scalar_vectors_to_bytes<T, B: AsRef<[u8]>, F: Fn(&T) -> B>(vectors: &[&[T]], encode: F) -> Vec<u8> {
+    scalar_vectors_to_bytes_with_trailer(vectors, encode, &[])
+}
+
+fn scalar_vectors_to_bytes_with_trailer<T, B: AsRef<[u8]>, F: Fn(&T) -> B>(vectors: &[&[T]], encode: F, trailer: &[&[u8]]) -> Vec<u8> {
+    let n = vectors[0].len();
+    let trailer_len: usize = trailer.iter().map(|part| part.len()).sum();
+    let mut bytes = Vec::with_capacity(8 + 32 * n * vectors.len() + trailer_len);
+    bytes.extend_from_slice(&(n as u64).to_le_bytes());
+    for vector in vectors {
+        assert_eq!(vector.len(), n);
+        for scalar in vector.iter() {
+            bytes.extend_from_slice(encode(scalar).as_ref());
+        }
+    }
+    for part in trailer {
+        bytes.extend_from_slice(part);
+    }
+    bytes
+}
+
+/// Writes CML shares as `n` followed by every share, each followed by its
+/// coefficient vector if the shares have been refreshed.
+pub(crate) fn shares_to_bytes<T, B: AsRef<[u8]>, F: Fn(&T) -> B>(shares: &[&[T]], coefficients: Option<&[&[T]]>, encode: F) -> Vec<u8> {
+    match coefficients {
+        Some(coefficients) => {
+            let vectors: Vec<&[T]> = shares.iter().zip(coefficients).flat_map(|(share, c)| std::iter::once(*share).chain(std::iter::once(*c))).collect();
+            scalar_vectors_to_bytes(&vectors, encode)
+        }
+        None => scalar_vectors_to_bytes(shares, encode),
+    }
+}
+
+/// Reads `count` shares written by `shares_to_bytes`, with their coefficient
+/// vectors if the length says they are there.
+#[allow(clippy::type_complexity)]
+pub(crate) fn shares_from_bytes<T, F: Fn(&[u8]) -> Result<T, Error>>(bytes: &[u8], count: usize, decode: F) -> Result<(Vec<Vec<T>>, Option<Vec<Vec<T>>>), Error> {
+    match scalar_vectors_from_bytes(bytes, 2 * count, &decode) {
+        Ok(vectors) => {
+            let (mut shares, mut coefficients) = (Vec::with_capacity(count), Vec::with_capacity(count));
+            for (i, vector) in vectors.into_iter().enumerate() {
+                if i % 2 == 0 { shares.push(vector) } else { coefficients.push(vector) }
+            }
+            Ok((shares, Some(coefficients)))
+        }
+        Err(Error::LengthMismatch { .. }) => Ok((scalar_vectors_from_bytes(bytes, count, decode)?, None)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes `vectors` as `scalar_vectors_to_bytes` does, followed by the
+/// hash-tilde version byte.
+pub(crate) fn versioned_vectors_to_bytes<T, B: AsRef<[u8]>, F: Fn(&T) -> B>(vectors: &[&[T]], version: HashTildeVersion, encode: F) -> Vec<u8> {
+    scalar_vectors_to_bytes_with_trailer(vectors, encode, &[&[version.to_byte()]])
+}
+
+/// Reads `count` vectors written by `versioned_vectors_to_bytes`.
+pub(crate) fn versioned_vectors_from_bytes<T, F: Fn(&[u8]) -> Result<T, Error>>(bytes: &[u8], count: usize, decode: F) -> Result<(Vec<Vec<T>>, HashTildeVersion), Error> {
+    let (version, vectors) = bytes.split_last().ok_or(Error::LengthMismatch { expected: 9, actual: 0 })?;
+    let vectors = scalar_vectors_from_bytes(vectors, count, decode).map_err(|e| match e {
+        Error::LengthMismatch { expected, actual } => Error::LengthMismatch { expected: expected.saturating_add(1), actual: actual + 1 },
+        e => e,
+    })?;
+    Ok((vectors, HashTildeVersion::from_byte(*version)?))
+}
+
+/// Reads `count` vectors of 32-byte scalars written by `scalar_vectors_to_bytes`.
+pub(crate) fn scalar_vectors_from_bytes<T, F: Fn(&[u8]) -> Result<T, Error>>(bytes: &[u8], count: usize, decode: F) -> Result<Vec<Vec<T>>, Error> {
+    scalar_vectors_from_bytes_with_len(bytes, count, 32, decode)
+}
+
+/// `scalar_vectors_from_bytes` for scalars of `scalar_len` bytes.
+pub(crate) fn scalar_vectors_from_bytes_with_len<T, F: Fn(&[u8]) -> Result<T, Error>>(bytes: &[u8], count: usize, scalar_len: usize, decode: F) -> Result<Vec<Vec<T>>, Error> {
+    if bytes.len() < 8 {
+        return Err(Error::LengthMismatch { expected: 8, actual: bytes.len() });
+    }
+    let n = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    if n == 0 {
+        return Err(Error::EmptySecretKey);
+    }
+    let expected = usize::try_from(n).ok()
+        .and_then(|n| n.checked_mul(scalar_len))
+        .and_then(|len| len.checked_mul(count))
+        .and_then(|len| len.checked_add(8))
+        .unwrap_or(usize::MAX);
+    check_length(bytes, expected)?;
+    let n = n as usize;
+    let mut chunks = bytes[8..].chunks_exact(scalar_len);
+    (0..count)
+        .map(|_| chunks.by_ref().take(n).map(&decode).collect())
+        .collect()
+}
+
+pub(crate) struct BytesVisitor;
+
+impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte string")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Implements `Serialize`/`Deserialize` on top of `ByteEncoding`.
+macro_rules! impl_serde_via_bytes {
+    ($t:ident<$g:ident: $bound:path>) => {
+        impl<$g: $bound> serde::Serialize for $t<$g> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(&zeroize::Zeroizing::new($crate::ByteEncoding::to_bytes(self)))
+            }
+        }
+
+        impl<'de, $g: $bound> serde::Deserialize<'de> for $t<$g> {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = zeroize::Zeroizing::new(deserializer.deserialize_bytes($crate::encoding::BytesVisitor)?);
+                <$t<$g> as $crate::ByteEncoding>::from_bytes(&bytes).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+    ($t:ident) => {
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(&zeroize::Zeroizing::new($crate::ByteEncoding::to_bytes(self)))
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = zeroize::Zeroizing::new(deserializer.deserialize_bytes($crate::encoding::BytesVisitor)?);
+                <$t as $crate::ByteEncoding>::from_bytes(&bytes).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Bytes that do not decode to a point of the expected group.
+    MalformedPoint,
+    /// A scalar encoding that is not reduced modulo the group order.
+    NonCanonicalScalar,
+    /// The public key is the identity element, or for Ed25519 any point of
+    /// small order.
+    IdentityPublicKey,
+    /// An input does not have the expected length.
+    LengthMismatch { expected: usize, actual: usize },
+    /// The signature does not verify under the given public key and message.
+    SignatureInvalid,
+    /// The nonce drawn while signing made the signature degenerate; signing
+    /// again with fresh randomness fixes it.
+    DegenerateNonce,
+    /// A signature scalar that must be nonzero is zero.
+    ZeroScalar,
+    /// A freshly computed signature did not verify, so it was withheld: a
+    /// fault during signing may have corrupted it and could leak the key.
+    FaultDetected,
+    /// An aggregate signature covers the same message twice, which lets a
+    /// rogue public key cancel out the others.
+    DuplicateMessage,
+    /// A multi-base LR-Okamoto key or signature uses `m` generators, outside
+    /// `1..=MAX_BASES`.
+    UnsupportedBaseCount { m: usize },
+    /// A secret vector aggregates to zero, which is not a valid private key.
+    ZeroAggregate,
+    /// An encoded CML key names a hash-tilde version this crate does not know.
+    UnknownHashVersion(u8),
+    /// A secret vector of length zero, which has nothing to aggregate.
+    EmptySecretKey,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MalformedPoint => f.write_str("malformed point"),
+            Error::NonCanonicalScalar => f.write_str("non-canonical scalar"),
+            Error::IdentityPublicKey => f.write_str("public key is the identity"),
+            Error::LengthMismatch { expected, actual } => write!(f, "expected {} bytes, got {}", expected, actual),
+            Error::SignatureInvalid => f.write_str("signature is invalid"),
+            Error::DegenerateNonce => f.write_str("degenerate nonce"),
+            Error::ZeroScalar => f.write_str("zero scalar"),
+            Error::FaultDetected => f.write_str("signature failed verification after signing"),
+            Error::DuplicateMessage => f.write_str("aggregate covers a message twice"),
+            Error::UnsupportedBaseCount { m } => write!(f, "unsupported number of generators {}", m),
+            Error::ZeroAggregate => f.write_str("secret vector aggregates to zero"),
+            Error::UnknownHashVersion(byte) => write!(f, "unknown hash-tilde version {}", byte),
+            Error::EmptySecretKey => f.write_str("secret vector is empty"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
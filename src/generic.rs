@@ -0,0 +1,438 @@
+//! The LR compiler over any prime-order group.
+//!
+//! `schnorr`, `ecdsa` and `okamoto` are LR-Schnorr, LR-ECDSA and LR-Okamoto
+//! with the group left as a type parameter `G: LrGroup`. Secret vectors,
+//! coefficients and nonces live in `G::Scalar` and go through the
+//! `*_prime_field` helpers, so a new group only needs an `LrGroup` impl.
+//! Ristretto (through `Ristretto`), BLS12-381 G1 and G2, secp256k1 and P-256
+//! are provided.
+//!
+//! Digests are reduced with the `WideReduce` of the scalar field, so on
+//! Ristretto the schemes compute exactly what `schnorr`, `ecdsa` and
+//! `okamoto_aim` do; those modules are wrappers that keep their dalek types
+//! and encodings. Here points and scalars are encoded with `GroupEncoding`
+//! and `PrimeField::to_repr`, which for Ristretto are the same bytes.
+//!
+//! `okamoto_cml` is not built on this layer. Its key is a pair of shares
+//! refreshed in place, and its two-party protocol in `okamoto_cml::split`
+//! exchanges dalek points and scalars, so there is no single-vector scheme to
+//! wrap. It still reaches the `*_prime_field` helpers through the
+//! `*_curve25519` ones.
+
+use bls12_381::{G1Projective, G2Projective};
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use std::convert::TryInto;
+use ff::{Field, PrimeField};
+use group::prime::PrimeGroup;
+use group::{Group, GroupEncoding};
+use k256::elliptic_curve::{AffineXCoordinate, IsHigh};
+use k256::elliptic_curve::ops::Reduce;
+use sha2::{Digest, Sha256, Sha512};
+use subtle::ConditionallySelectable;
+use zeroize::Zeroize;
+
+use crate::encoding::{check_length, prime_field_from_bytes, scalar_vectors_from_bytes_with_len, scalar_vectors_to_bytes};
+use crate::secret::SecretVec;
+use crate::{ByteEncoding, SecretEncoding, Error, WideReduce, OKAMOTO_BASE_POINT2, OKAMOTO_BASE_POINT2_DOMAIN, assert_nonempty};
+
+mod ristretto;
+
+pub mod ecdsa;
+pub mod okamoto;
+pub mod schnorr;
+
+pub use ristretto::{Ristretto, RistrettoScalar};
+pub(crate) use ristretto::{from_ristretto_scalars, to_ristretto_scalars};
+
+/// A prime-order group the generic schemes can run over.
+pub trait LrGroup: PrimeGroup<Scalar = <Self as LrGroup>::LrScalar> {
+    /// The scalar field, which `Group::Scalar` is always equal to.
+    type LrScalar: PrimeField + Zeroize + WideReduce;
+
+    /// Separates the nonce derivations of different groups.
+    const NAME: &'static str;
+
+    /// Hashes `domain` to an element whose discrete logarithm nobody knows,
+    /// such as the second LR-Okamoto generator.
+    fn hash_to_group(domain: &[u8]) -> Self;
+
+    /// The second LR-Okamoto generator, `hash_to_group` of
+    /// `OKAMOTO_BASE_POINT2_DOMAIN`. The provided groups cache it.
+    fn okamoto_base_point2() -> Self {
+        Self::hash_to_group(OKAMOTO_BASE_POINT2_DOMAIN)
+    }
+
+    /// The message hash of LR-ECDSA, by default the SHA-512 digest of `msg`
+    /// reduced by `hash_to_scalar`.
+    fn ecdsa_message_hash(msg: &[u8]) -> Self::Scalar {
+        hash_to_scalar(&[msg])
+    }
+
+    /// The conversion function of LR-ECDSA, by default the encoding of
+    /// `point` read as a big-endian integer modulo the group order.
+    fn ecdsa_conversion(point: &Self) -> Self::Scalar {
+        let base = Self::Scalar::from(256);
+        point.to_bytes().as_ref().iter().fold(Self::Scalar::zero(), |acc, byte| acc * base + Self::Scalar::from(*byte as u64))
+    }
+
+    /// Maps `s` to the one of `s` and `-s` that verifiers of the group
+    /// accept. Both verify, so by default `s` is kept.
+    fn ecdsa_normalize_s(s: Self::Scalar) -> Self::Scalar {
+        s
+    }
+}
+
+/// Domain separation tag for `hash_to_group` on BLS12-381.
+const HASH_TO_GROUP_DST: &[u8] = b"practical-lr-signature/generic/hash-to-group";
+
+impl LrGroup for Ristretto {
+    type LrScalar = RistrettoScalar;
+
+    const NAME: &'static str = "ristretto255";
+
+    fn hash_to_group(domain: &[u8]) -> Self {
+        Ristretto(RistrettoPoint::hash_from_bytes::<Sha512>(domain))
+    }
+
+    fn okamoto_base_point2() -> Self {
+        Ristretto(*OKAMOTO_BASE_POINT2)
+    }
+
+    /// The encoding read as a little-endian integer, as the `ecdsa` module
+    /// has always done.
+    fn ecdsa_conversion(point: &Self) -> RistrettoScalar {
+        RistrettoScalar(Scalar::from_bytes_mod_order(point.to_bytes()))
+    }
+}
+
+impl LrGroup for G1Projective {
+    type LrScalar = bls12_381::Scalar;
+
+    const NAME: &'static str = "BLS12-381 G1";
+
+    fn hash_to_group(domain: &[u8]) -> Self {
+        <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(domain, HASH_TO_GROUP_DST)
+    }
+
+    fn okamoto_base_point2() -> Self {
+        lazy_static::lazy_static! {
+            static ref BASE_POINT2: G1Projective = G1Projective::hash_to_group(OKAMOTO_BASE_POINT2_DOMAIN);
+        }
+        *BASE_POINT2
+    }
+}
+
+impl LrGroup for G2Projective {
+    type LrScalar = bls12_381::Scalar;
+
+    const NAME: &'static str = "BLS12-381 G2";
+
+    fn hash_to_group(domain: &[u8]) -> Self {
+        <G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(domain, HASH_TO_GROUP_DST)
+    }
+
+    fn okamoto_base_point2() -> Self {
+        lazy_static::lazy_static! {
+            static ref BASE_POINT2: G2Projective = G2Projective::hash_to_group(OKAMOTO_BASE_POINT2_DOMAIN);
+        }
+        *BASE_POINT2
+    }
+}
+
+// secp256k1 and P-256 follow standard ECDSA-SHA256, so that LR-ECDSA over
+// them is plain ECDSA.
+impl LrGroup for k256::ProjectivePoint {
+    type LrScalar = k256::Scalar;
+
+    const NAME: &'static str = "secp256k1";
+
+    fn hash_to_group(domain: &[u8]) -> Self {
+        hash_to_compressed_point(domain)
+    }
+
+    fn okamoto_base_point2() -> Self {
+        lazy_static::lazy_static! {
+            static ref BASE_POINT2: k256::ProjectivePoint = hash_to_compressed_point(OKAMOTO_BASE_POINT2_DOMAIN);
+        }
+        *BASE_POINT2
+    }
+
+    fn ecdsa_message_hash(msg: &[u8]) -> k256::Scalar {
+        <k256::Scalar as Reduce<k256::U256>>::from_be_bytes_reduced(Sha256::digest(msg))
+    }
+
+    fn ecdsa_conversion(point: &Self) -> k256::Scalar {
+        <k256::Scalar as Reduce<k256::U256>>::from_be_bytes_reduced(point.to_affine().x())
+    }
+
+    /// Low `s`, which Bitcoin and the `k256` verifier require.
+    fn ecdsa_normalize_s(s: k256::Scalar) -> k256::Scalar {
+        k256::Scalar::conditional_select(&s, &-s, s.is_high())
+    }
+}
+
+impl LrGroup for p256::ProjectivePoint {
+    type LrScalar = p256::Scalar;
+
+    const NAME: &'static str = "P-256";
+
+    fn hash_to_group(domain: &[u8]) -> Self {
+        hash_to_compressed_point(domain)
+    }
+
+    fn okamoto_base_point2() -> Self {
+        lazy_static::lazy_static! {
+            static ref BASE_POINT2: p256::ProjectivePoint = hash_to_compressed_point(OKAMOTO_BASE_POINT2_DOMAIN);
+        }
+        *BASE_POINT2
+    }
+
+    fn ecdsa_message_hash(msg: &[u8]) -> p256::Scalar {
+        <p256::Scalar as Reduce<p256::U256>>::from_be_bytes_reduced(Sha256::digest(msg))
+    }
+
+    fn ecdsa_conversion(point: &Self) -> p256::Scalar {
+        <p256::Scalar as Reduce<p256::U256>>::from_be_bytes_reduced(point.to_affine().x())
+    }
+}
+
+// Try-and-increment over SEC 1 compressed points `0x02 || SHA-256(domain ||
+// counter)`. About half of all x-coordinates are on the curve. The input is
+// public, so the variable time does not matter.
+fn hash_to_compressed_point<G: GroupEncoding>(domain: &[u8]) -> G {
+    (0u64..).find_map(|counter| {
+        let mut repr = G::Repr::default();
+        let bytes = repr.as_mut();
+        bytes[0] = 0x02;
+        bytes[1..].copy_from_slice(&Sha256::new().chain(domain).chain(counter.to_le_bytes()).finalize());
+        Option::from(G::from_bytes(&repr))
+    }).unwrap()
+}
+
+/// Reduces the SHA-512 digest of the concatenated `parts` with `WideReduce`.
+pub(crate) fn hash_to_scalar<F: WideReduce>(parts: &[&[u8]]) -> F {
+    let mut hash = Sha512::default();
+    for part in parts {
+        hash.update(part);
+    }
+    F::from_wide(hash.finalize().as_slice().try_into().unwrap())
+}
+
+fn scalar_len<F: PrimeField>() -> usize {
+    F::Repr::default().as_ref().len()
+}
+
+/// Decodes `count` scalars of `F`, each in its `to_repr` form.
+pub(crate) fn scalars_from_bytes<F: PrimeField>(bytes: &[u8], count: usize) -> Result<Vec<F>, Error> {
+    let len = scalar_len::<F>();
+    check_length(bytes, count * len)?;
+    bytes.chunks(len).map(prime_field_from_bytes).collect()
+}
+
+pub(crate) fn scalars_to_bytes<F: PrimeField>(scalars: &[F]) -> Vec<u8> {
+    scalars.iter().flat_map(|scalar| scalar.to_repr().as_ref().to_vec()).collect()
+}
+
+pub(crate) fn point_from_bytes<G: GroupEncoding>(bytes: &[u8]) -> Result<G, Error> {
+    let mut repr = G::Repr::default();
+    check_length(bytes, repr.as_ref().len())?;
+    repr.as_mut().copy_from_slice(bytes);
+    Option::from(G::from_bytes(&repr)).ok_or(Error::MalformedPoint)
+}
+
+/// Rejects the identity. `G` has prime order, so every other element
+/// generates it.
+pub(crate) fn validate_public_key<G: Group>(pk: &G) -> Result<(), Error> {
+    if bool::from(pk.is_identity()) {
+        return Err(Error::IdentityPublicKey);
+    }
+    Ok(())
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by the `to_repr` form of
+/// `n` scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey<G: LrGroup>(pub(crate) SecretVec<G::Scalar>);
+
+impl<G: LrGroup> SigningKey<G> {
+    pub fn as_scalars(&self) -> &[G::Scalar] {
+        &self.0
+    }
+}
+
+impl<G: LrGroup> From<Vec<G::Scalar>> for SigningKey<G> {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<G::Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl<G: LrGroup> std::fmt::Debug for SigningKey<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey<{}>(n = {})", G::NAME, self.0.len())
+    }
+}
+
+impl<G: LrGroup> ByteEncoding for SigningKey<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], |s| s.to_repr())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes_with_len(bytes, 1, scalar_len::<G::Scalar>(), prime_field_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl<G: LrGroup> SecretEncoding for SigningKey<G> {}
+
+/// The compressed public key of a generic scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey<G: LrGroup>(pub G);
+
+impl<G: LrGroup> VerifyingKey<G> {
+    /// Rejects the identity.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_public_key(&self.0)
+    }
+}
+
+impl<G: LrGroup> ByteEncoding for VerifyingKey<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().as_ref().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(point_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+impl_serde_via_bytes!(SigningKey<G: LrGroup>);
+impl_serde_via_bytes!(VerifyingKey<G: LrGroup>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Params, PublicParams, SignatureScheme};
+
+    fn roundtrip<S: SignatureScheme<Params = Params>>() where S::Signature: ByteEncoding, S::PublicKey: ByteEncoding + PartialEq + std::fmt::Debug, S::SecretKey: ByteEncoding + PartialEq {
+        let params = Params::new(8);
+        let (sk, pk) = S::keygen(&params);
+        let signature = S::sign(&params, &sk, &pk, b"Hello, world!");
+        assert!(S::verify(&params, &pk, b"Hello, world!", &signature));
+        assert!(!S::verify(&params, &pk, b"Hello, world?", &signature));
+        let signature = S::Signature::from_bytes(&signature.to_bytes()).unwrap();
+        assert!(S::verify(&params, &pk, b"Hello, world!", &signature));
+        assert_eq!(S::PublicKey::from_bytes(&pk.to_bytes()).unwrap(), pk);
+        assert!(S::SecretKey::from_bytes(&sk.to_bytes()).unwrap() == sk);
+    }
+
+    fn roundtrip_all<G: LrGroup>() {
+        roundtrip::<schnorr::Schnorr<G>>();
+        roundtrip::<ecdsa::Ecdsa<G>>();
+        roundtrip::<okamoto::Okamoto<G>>();
+    }
+
+    #[test]
+    fn test_generic_ristretto() {
+        roundtrip_all::<Ristretto>();
+    }
+
+    #[test]
+    fn test_generic_bls12_381() {
+        roundtrip_all::<G1Projective>();
+        roundtrip_all::<G2Projective>();
+    }
+
+    #[test]
+    fn test_generic_secp256k1_and_p256() {
+        roundtrip_all::<k256::ProjectivePoint>();
+        roundtrip_all::<p256::ProjectivePoint>();
+    }
+
+    #[test]
+    fn test_generic_identity_public_key_rejected() {
+        let identity = G1Projective::identity();
+        assert_eq!(VerifyingKey::<G1Projective>::from_bytes(identity.to_bytes().as_ref()), Err(Error::IdentityPublicKey));
+        let (sk, _) = schnorr::keygen::<G1Projective>(8);
+        let signature = schnorr::sign(&identity, &sk, b"msg");
+        assert_eq!(schnorr::try_verify(&identity, b"msg", &signature), Err(Error::IdentityPublicKey));
+    }
+
+    #[test]
+    fn test_ristretto_hash_to_group_matches_okamoto_g2() {
+        let (g_1, g_2) = okamoto::generators::<Ristretto>();
+        assert_eq!(g_1.0, PublicParams::standard().g1);
+        assert_eq!(g_2.0, PublicParams::standard().g2);
+    }
+
+    #[test]
+    fn test_okamoto_base_point2_is_cached_hash() {
+        fn check<G: LrGroup>() {
+            assert_eq!(G::okamoto_base_point2(), G::hash_to_group(crate::OKAMOTO_BASE_POINT2_DOMAIN));
+        }
+        check::<Ristretto>();
+        check::<G1Projective>();
+        check::<G2Projective>();
+        check::<k256::ProjectivePoint>();
+        check::<p256::ProjectivePoint>();
+    }
+
+    #[test]
+    fn test_generic_ristretto_matches_dedicated_modules() {
+        use curve25519_dalek::scalar::Scalar;
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let sk: Vec<Scalar> = (1..=20u64).map(Scalar::from).collect();
+        let generic_sk = to_ristretto_scalars(&sk);
+        let pk = crate::schnorr::keygen_with_rng(1, &mut ChaCha20Rng::from_seed([7u8; 32])).1;
+        let rng = || ChaCha20Rng::from_seed([9u8; 32]);
+        let msg = b"Hello, world!";
+
+        let (c, z) = schnorr::sign_with_rng(&Ristretto(pk), generic_sk, msg, &mut rng());
+        assert_eq!((c.0, z.0), crate::schnorr::sign_with_rng(&pk, &sk, msg, &mut rng()));
+        let (r, s) = ecdsa::sign_with_rng::<Ristretto, _>(generic_sk, msg, &mut rng());
+        assert_eq!((r.0, s.0), crate::ecdsa::sign_with_rng(&sk, msg, &mut rng()));
+        let (c, z_1, z_2) = okamoto::sign_with_rng(&Ristretto(pk), generic_sk, msg, &mut rng());
+        assert_eq!((c.0, z_1.0, z_2.0), crate::okamoto_aim::sign_with_rng(&pk, &sk, msg, &mut rng()));
+
+        let params = Params::new(20);
+        let generic_pk = schnorr::Schnorr::<Ristretto>::public_key(&params, &SigningKey::from(generic_sk.to_vec()));
+        let pk = crate::Schnorr::public_key(&params, &crate::schnorr::SigningKey::from(sk));
+        assert_eq!(generic_pk.to_bytes(), pk.to_bytes());
+    }
+
+    #[test]
+    fn test_ristretto_scalar_field() {
+        let root = RistrettoScalar::root_of_unity();
+        assert_eq!(root.square(), -RistrettoScalar::one());
+        for _ in 0..16 {
+            let x = <RistrettoScalar as Field>::random(rand_core::OsRng);
+            let root = Option::<RistrettoScalar>::from(x.square().sqrt()).unwrap();
+            assert!(root == x || root == -x);
+        }
+        assert!(bool::from(RistrettoScalar::from(2).sqrt().is_none()));
+        assert!(bool::from(RistrettoScalar::from_repr([0xff; 32]).is_none()));
+    }
+
+    #[test]
+    fn test_generic_deterministic_signing() {
+        type K256Okamoto = okamoto::Okamoto<k256::ProjectivePoint>;
+        let params = Params::new(8);
+        let (sk, pk) = K256Okamoto::keygen(&params);
+        let signature = K256Okamoto::sign_deterministic(&params, &sk, &pk, b"msg");
+        assert_eq!(signature, K256Okamoto::sign_deterministic(&params, &sk, &pk, b"msg"));
+        assert_ne!(signature, K256Okamoto::sign_deterministic(&params, &sk, &pk, b"other"));
+        assert!(K256Okamoto::verify(&params, &pk, b"msg", &signature));
+        let (sk, pk) = ecdsa::Ecdsa::<Ristretto>::keygen(&params);
+        let signature = ecdsa::Ecdsa::<Ristretto>::sign_deterministic(&params, &sk, &pk, b"msg");
+        assert_eq!(signature, ecdsa::Ecdsa::<Ristretto>::sign_deterministic(&params, &sk, &pk, b"msg"));
+        assert!(ecdsa::Ecdsa::<Ristretto>::verify(&params, &pk, b"msg", &signature));
+    }
+}
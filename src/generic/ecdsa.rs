@@ -0,0 +1,187 @@
+//! LR-ECDSA over any `LrGroup`.
+//!
+//! The message hash and the conversion from a point to `r` come from the
+//! group. A generic group has no x-coordinate, so by default `r` is the whole
+//! point encoding reduced modulo the group order, as the Ristretto `ecdsa`
+//! module does. secp256k1 and P-256 use SHA-256 and the x-coordinate as in
+//! standard ECDSA, which `ecdsa_secp256k1` and `ecdsa_p256` build on.
+
+use ff::Field;
+use rand::{CryptoRng, RngCore, thread_rng};
+use std::marker::PhantomData;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{ByteEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_prime_field, sample_prime_field_lambda_with_rng, hash_tilde_prime_field, prime_field_list_to_bytes, retry_degenerate, validate_nonempty};
+use super::{LrGroup, scalars_from_bytes, scalars_to_bytes, validate_public_key};
+
+pub use super::{SigningKey, VerifyingKey};
+
+/// Panics if `n == 0`.
+pub fn keygen<G: LrGroup>(n: usize) -> (Vec<G::Scalar>, G) {
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<G: LrGroup, R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<G::Scalar>, G) {
+    keygen_inner(&Params::new(n), rng)
+}
+
+pub fn sign<G: LrGroup>(sk: &[G::Scalar], msg: &[u8]) -> (G::Scalar, G::Scalar) {
+    sign_with_rng::<G, _>(sk, msg, &mut thread_rng())
+}
+
+/// Panics if `sk` is empty.
+pub fn sign_with_rng<G: LrGroup, R: RngCore + CryptoRng>(sk: &[G::Scalar], msg: &[u8], rng: &mut R) -> (G::Scalar, G::Scalar) {
+    retry_degenerate(Ecdsa::<G>::NAME, || try_sign_with_rng::<G, R>(sk, msg, rng))
+}
+
+pub fn try_sign_with_rng<G: LrGroup, R: RngCore + CryptoRng>(sk: &[G::Scalar], msg: &[u8], rng: &mut R) -> Result<(G::Scalar, G::Scalar), Error> {
+    validate_nonempty(sk)?;
+    sign_inner(&expand_inner::<G>(HashTildeVersion::default(), sk), msg, rng)
+}
+
+pub fn verify<G: LrGroup>(pk: &G, msg: &[u8], signature: &(G::Scalar, G::Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify<G: LrGroup>(pk: &G, msg: &[u8], signature: &(G::Scalar, G::Scalar)) -> Result<(), Error> {
+    let (r, s) = signature;
+    validate_public_key(pk)?;
+    validate_nonzero::<G>(r, s)?;
+    let point = (G::generator() * G::ecdsa_message_hash(msg) + *pk * r) * s.invert().unwrap();
+    if r == &G::ecdsa_conversion(&point) {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+fn validate_nonzero<G: LrGroup>(r: &G::Scalar, s: &G::Scalar) -> Result<(), Error> {
+    if bool::from(r.is_zero() | s.is_zero()) {
+        return Err(Error::ZeroScalar);
+    }
+    Ok(())
+}
+
+// Resamples the rare secret vector that aggregates to zero, whose public key
+// is the identity.
+pub(crate) fn keygen_inner<G: LrGroup, R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<G::Scalar>, G) {
+    assert_nonempty(params.n);
+    loop {
+        let sk = sample_prime_field_lambda_with_rng(params.n, rng);
+        let pk = public_key_inner::<G>(params.hash_version, &sk);
+        if !bool::from(pk.is_identity()) {
+            return (sk, pk);
+        }
+    }
+}
+
+pub(crate) fn expand_inner<G: LrGroup>(version: HashTildeVersion, sk: &[G::Scalar]) -> ExpandedSigningKey<G> {
+    assert_nonempty(sk.len());
+    let n = sk.len();
+    let a_list = Zeroizing::new(hash_tilde_prime_field(&prime_field_list_to_bytes(sk), n, false, version));
+    ExpandedSigningKey { n, version, x: aggregate_prime_field(&a_list, sk) }
+}
+
+pub(crate) fn public_key_inner<G: LrGroup>(version: HashTildeVersion, sk: &[G::Scalar]) -> G {
+    G::generator() * expand_inner::<G>(version, sk).x
+}
+
+pub(crate) fn sign_inner<G: LrGroup, R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, msg: &[u8], rng: &mut R) -> Result<(G::Scalar, G::Scalar), Error> {
+    let (n, version) = (esk.n, esk.version);
+    let msg_hash = G::ecdsa_message_hash(msg);
+    let k_list = Zeroizing::new(sample_prime_field_lambda_with_rng::<G::Scalar, R>(n, rng));
+    let b_list = Zeroizing::new(hash_tilde_prime_field(&prime_field_list_to_bytes(&k_list), n, true, version));
+    let k = Zeroizing::new(aggregate_prime_field(&b_list, &k_list));
+    let k_inv = Option::<G::Scalar>::from(k.invert()).ok_or(Error::DegenerateNonce)?;
+    let r = G::ecdsa_conversion(&(G::generator() * *k));
+    let s = k_inv * (msg_hash + r * esk.x);
+    validate_nonzero::<G>(&r, &s).map_err(|_| Error::DegenerateNonce)?;
+    Ok((r, G::ecdsa_normalize_s(s)))
+}
+
+/// The aggregated secret `<a, sk>`, with the length and hash version used to
+/// derive nonces.
+#[derive(Clone)]
+pub struct ExpandedSigningKey<G: LrGroup> {
+    pub(crate) n: usize,
+    pub(crate) version: HashTildeVersion,
+    pub(crate) x: G::Scalar,
+}
+
+impl<G: LrGroup> std::fmt::Debug for ExpandedSigningKey<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ExpandedSigningKey<{}>(n = {})", G::NAME, self.n)
+    }
+}
+
+impl<G: LrGroup> Drop for ExpandedSigningKey<G> {
+    fn drop(&mut self) {
+        self.x.zeroize();
+    }
+}
+
+/// `r || s`, two nonzero scalars in their `to_repr` form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature<G: LrGroup> {
+    pub r: G::Scalar,
+    pub s: G::Scalar,
+}
+
+impl<G: LrGroup> From<(G::Scalar, G::Scalar)> for Signature<G> {
+    fn from((r, s): (G::Scalar, G::Scalar)) -> Self {
+        Signature { r, s }
+    }
+}
+
+impl<G: LrGroup> From<Signature<G>> for (G::Scalar, G::Scalar) {
+    fn from(signature: Signature<G>) -> Self {
+        (signature.r, signature.s)
+    }
+}
+
+impl<G: LrGroup> ByteEncoding for Signature<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalars_to_bytes(&[self.r, self.s])
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let scalars = scalars_from_bytes(bytes, 2)?;
+        validate_nonzero::<G>(&scalars[0], &scalars[1])?;
+        Ok(Signature { r: scalars[0], s: scalars[1] })
+    }
+}
+
+impl_serde_via_bytes!(Signature<G: LrGroup>);
+
+pub struct Ecdsa<G>(PhantomData<G>);
+
+impl<G: LrGroup> SignatureScheme for Ecdsa<G> {
+    const NAME: &'static str = "Generic LR-ECDSA";
+
+    type Params = Params;
+    type SecretKey = SigningKey<G>;
+    type PublicKey = VerifyingKey<G>;
+    type Signature = Signature<G>;
+    type ExpandedSecretKey = ExpandedSigningKey<G>;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey<G>, VerifyingKey<G>) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey<G>) -> VerifyingKey<G> {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey<G>) -> ExpandedSigningKey<G> {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, _pk: &VerifyingKey<G>, msg: &[u8], rng: &mut R) -> Result<Signature<G>, Error> {
+        sign_inner(esk, msg, rng).map(Signature::from)
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey<G>, msg: &[u8], signature: &Signature<G>) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
+}
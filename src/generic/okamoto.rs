@@ -0,0 +1,193 @@
+//! LR-Okamoto (the `okamoto_aim` construction) over any `LrGroup`.
+//!
+//! The second generator is `G::okamoto_base_point2()`, which on Ristretto is
+//! the `g2` of `PublicParams::standard()`.
+
+use ff::PrimeField;
+use rand::{CryptoRng, RngCore, thread_rng};
+use std::marker::PhantomData;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{ByteEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_prime_field, sample_prime_field_lambda_with_rng, hash_tilde_prime_field, prime_field_list_to_bytes};
+use super::{LrGroup, hash_to_scalar, scalars_from_bytes, scalars_to_bytes, validate_public_key};
+
+pub use super::{SigningKey, VerifyingKey};
+
+/// The generators `(g1, g2)`: the group generator and
+/// `G::okamoto_base_point2()`.
+pub fn generators<G: LrGroup>() -> (G, G) {
+    (G::generator(), G::okamoto_base_point2())
+}
+
+pub fn keygen<G: LrGroup>(n: usize) -> (Vec<G::Scalar>, G) {
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<G: LrGroup, R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<G::Scalar>, G) {
+    keygen_inner(&Params::new(n), rng)
+}
+
+pub fn sign<G: LrGroup>(pk: &G, sk: &[G::Scalar], msg: &[u8]) -> (G::Scalar, G::Scalar, G::Scalar) {
+    sign_with_rng(pk, sk, msg, &mut thread_rng())
+}
+
+pub fn sign_with_rng<G: LrGroup, R: RngCore + CryptoRng>(pk: &G, sk: &[G::Scalar], msg: &[u8], rng: &mut R) -> (G::Scalar, G::Scalar, G::Scalar) {
+    let (_, c, z_1, z_2) = sign_inner(&expand_inner::<G>(HashTildeVersion::default(), sk), pk, msg, rng);
+    (c, z_1, z_2)
+}
+
+pub fn verify<G: LrGroup>(pk: &G, msg: &[u8], signature: &(G::Scalar, G::Scalar, G::Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify<G: LrGroup>(pk: &G, msg: &[u8], signature: &(G::Scalar, G::Scalar, G::Scalar)) -> Result<(), Error> {
+    let (c, z_1, z_2) = signature;
+    validate_public_key(pk)?;
+    let (g_1, g_2) = generators::<G>();
+    let r_point = g_1 * z_1 + g_2 * z_2 - *pk * c;
+    if c == &challenge(pk, msg, &r_point) {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+// The message is hashed to a scalar first, as the Ristretto modules do.
+pub(crate) fn challenge<G: LrGroup>(pk: &G, msg: &[u8], point: &G) -> G::Scalar {
+    let msg_hash: G::Scalar = hash_to_scalar(&[msg]);
+    hash_to_scalar(&[pk.to_bytes().as_ref(), msg_hash.to_repr().as_ref(), point.to_bytes().as_ref()])
+}
+
+pub(crate) fn keygen_inner<G: LrGroup, R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<G::Scalar>, G) {
+    assert_nonempty(params.n);
+    let sk = sample_prime_field_lambda_with_rng(params.n, rng);
+    let pk = public_key_inner::<G>(params.hash_version, &sk);
+    (sk, pk)
+}
+
+// The two aggregates of `list`, with coefficients derived from its encoding
+// followed by a 0 or a 1.
+fn aggregate_pair<G: LrGroup>(list: &[G::Scalar], version: HashTildeVersion) -> (G::Scalar, G::Scalar) {
+    let n = list.len();
+    let mut hash = prime_field_list_to_bytes(list);
+    hash.push(0);
+    let a_list = Zeroizing::new(hash_tilde_prime_field(&hash, n, false, version));
+    *hash.last_mut().unwrap() = 1;
+    let b_list = Zeroizing::new(hash_tilde_prime_field(&hash, n, false, version));
+    (aggregate_prime_field(&a_list, list), aggregate_prime_field(&b_list, list))
+}
+
+pub(crate) fn expand_inner<G: LrGroup>(version: HashTildeVersion, sk: &[G::Scalar]) -> ExpandedSigningKey<G> {
+    assert_nonempty(sk.len());
+    let (x_1, x_2) = aggregate_pair::<G>(sk, version);
+    ExpandedSigningKey { n: sk.len(), version, x_1, x_2 }
+}
+
+pub(crate) fn public_key_inner<G: LrGroup>(version: HashTildeVersion, sk: &[G::Scalar]) -> G {
+    let esk = expand_inner::<G>(version, sk);
+    let (g_1, g_2) = generators::<G>();
+    g_1 * esk.x_1 + g_2 * esk.x_2
+}
+
+/// Returns the commitment along with `(c, z_1, z_2)`.
+pub(crate) fn sign_inner<G: LrGroup, R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, pk: &G, msg: &[u8], rng: &mut R) -> (G, G::Scalar, G::Scalar, G::Scalar) {
+    let r_list = Zeroizing::new(sample_prime_field_lambda_with_rng::<G::Scalar, R>(esk.n, rng));
+    let mut k = aggregate_pair::<G>(&r_list, esk.version);
+    let (g_1, g_2) = generators::<G>();
+    let point = g_1 * k.0 + g_2 * k.1;
+    let c = challenge(pk, msg, &point);
+    let signature = (point, c, k.0 + c * esk.x_1, k.1 + c * esk.x_2);
+    k.0.zeroize();
+    k.1.zeroize();
+    signature
+}
+
+/// The aggregated secrets `<a, sk>` and `<b, sk>`, with the length and hash
+/// version used to derive nonces.
+#[derive(Clone)]
+pub struct ExpandedSigningKey<G: LrGroup> {
+    pub(crate) n: usize,
+    pub(crate) version: HashTildeVersion,
+    pub(crate) x_1: G::Scalar,
+    pub(crate) x_2: G::Scalar,
+}
+
+impl<G: LrGroup> std::fmt::Debug for ExpandedSigningKey<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ExpandedSigningKey<{}>(n = {})", G::NAME, self.n)
+    }
+}
+
+impl<G: LrGroup> Drop for ExpandedSigningKey<G> {
+    fn drop(&mut self) {
+        self.x_1.zeroize();
+        self.x_2.zeroize();
+    }
+}
+
+/// `c || z_1 || z_2`, three scalars in their `to_repr` form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature<G: LrGroup> {
+    pub c: G::Scalar,
+    pub z_1: G::Scalar,
+    pub z_2: G::Scalar,
+}
+
+impl<G: LrGroup> From<(G::Scalar, G::Scalar, G::Scalar)> for Signature<G> {
+    fn from((c, z_1, z_2): (G::Scalar, G::Scalar, G::Scalar)) -> Self {
+        Signature { c, z_1, z_2 }
+    }
+}
+
+impl<G: LrGroup> From<Signature<G>> for (G::Scalar, G::Scalar, G::Scalar) {
+    fn from(signature: Signature<G>) -> Self {
+        (signature.c, signature.z_1, signature.z_2)
+    }
+}
+
+impl<G: LrGroup> ByteEncoding for Signature<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalars_to_bytes(&[self.c, self.z_1, self.z_2])
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let scalars = scalars_from_bytes(bytes, 3)?;
+        Ok(Signature { c: scalars[0], z_1: scalars[1], z_2: scalars[2] })
+    }
+}
+
+impl_serde_via_bytes!(Signature<G: LrGroup>);
+
+pub struct Okamoto<G>(PhantomData<G>);
+
+impl<G: LrGroup> SignatureScheme for Okamoto<G> {
+    const NAME: &'static str = "Generic LR-Okamoto";
+
+    type Params = Params;
+    type SecretKey = SigningKey<G>;
+    type PublicKey = VerifyingKey<G>;
+    type Signature = Signature<G>;
+    type ExpandedSecretKey = ExpandedSigningKey<G>;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey<G>, VerifyingKey<G>) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey<G>) -> VerifyingKey<G> {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey<G>) -> ExpandedSigningKey<G> {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, pk: &VerifyingKey<G>, msg: &[u8], rng: &mut R) -> Result<Signature<G>, Error> {
+        let (_, c, z_1, z_2) = sign_inner(esk, &pk.0, msg, rng);
+        Ok(Signature { c, z_1, z_2 })
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey<G>, msg: &[u8], signature: &Signature<G>) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
+}
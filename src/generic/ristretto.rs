@@ -0,0 +1,277 @@
+//! `group` and `ff` implementations for Ristretto, which curve25519-dalek 3
+//! does not provide.
+
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use ff::{Field, PrimeField};
+use group::{Group, GroupEncoding};
+use group::prime::PrimeGroup;
+use rand_core::RngCore;
+use std::iter::Sum;
+use std::mem::ManuallyDrop;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+use zeroize::Zeroize;
+
+use crate::WideReduce;
+
+/// A Ristretto point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Ristretto(pub RistrettoPoint);
+
+/// A scalar modulo the Ristretto group order `l = 2^252 + 27742317777372353535851937790883648493`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct RistrettoScalar(pub Scalar);
+
+// Implements `$op` and `$op_assign` for every combination of owned and
+// borrowed operands the `group` and `ff` traits ask for.
+macro_rules! impl_op {
+    ($lhs:ty, $rhs:ty, $op:ident, $f:ident, $op_assign:ident, $f_assign:ident) => {
+        impl $op<$rhs> for $lhs {
+            type Output = $lhs;
+
+            fn $f(self, rhs: $rhs) -> $lhs {
+                <$lhs>::from((self.0).$f(rhs.0))
+            }
+        }
+
+        impl<'a> $op<&'a $rhs> for $lhs {
+            type Output = $lhs;
+
+            fn $f(self, rhs: &'a $rhs) -> $lhs {
+                <$lhs>::from((self.0).$f(rhs.0))
+            }
+        }
+
+        impl $op_assign<$rhs> for $lhs {
+            fn $f_assign(&mut self, rhs: $rhs) {
+                *self = (*self).$f(rhs);
+            }
+        }
+
+        impl<'a> $op_assign<&'a $rhs> for $lhs {
+            fn $f_assign(&mut self, rhs: &'a $rhs) {
+                *self = (*self).$f(rhs);
+            }
+        }
+    };
+}
+
+impl From<RistrettoPoint> for Ristretto {
+    fn from(point: RistrettoPoint) -> Self {
+        Ristretto(point)
+    }
+}
+
+impl From<Scalar> for RistrettoScalar {
+    fn from(scalar: Scalar) -> Self {
+        RistrettoScalar(scalar)
+    }
+}
+
+impl_op!(RistrettoScalar, RistrettoScalar, Add, add, AddAssign, add_assign);
+impl_op!(RistrettoScalar, RistrettoScalar, Sub, sub, SubAssign, sub_assign);
+impl_op!(RistrettoScalar, RistrettoScalar, Mul, mul, MulAssign, mul_assign);
+impl_op!(Ristretto, Ristretto, Add, add, AddAssign, add_assign);
+impl_op!(Ristretto, Ristretto, Sub, sub, SubAssign, sub_assign);
+impl_op!(Ristretto, RistrettoScalar, Mul, mul, MulAssign, mul_assign);
+
+impl Neg for RistrettoScalar {
+    type Output = RistrettoScalar;
+
+    fn neg(self) -> RistrettoScalar {
+        RistrettoScalar(-self.0)
+    }
+}
+
+impl Neg for Ristretto {
+    type Output = Ristretto;
+
+    fn neg(self) -> Ristretto {
+        Ristretto(-self.0)
+    }
+}
+
+impl From<u64> for RistrettoScalar {
+    fn from(value: u64) -> Self {
+        RistrettoScalar(Scalar::from(value))
+    }
+}
+
+impl ConditionallySelectable for RistrettoScalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        RistrettoScalar(Scalar::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl ConstantTimeEq for RistrettoScalar {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl Zeroize for RistrettoScalar {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Field for RistrettoScalar {
+    fn random(mut rng: impl RngCore) -> Self {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        let scalar = RistrettoScalar(Scalar::from_bytes_mod_order_wide(&bytes));
+        bytes.zeroize();
+        scalar
+    }
+
+    fn zero() -> Self {
+        RistrettoScalar(Scalar::zero())
+    }
+
+    fn one() -> Self {
+        RistrettoScalar(Scalar::one())
+    }
+
+    fn square(&self) -> Self {
+        RistrettoScalar(self.0 * self.0)
+    }
+
+    fn double(&self) -> Self {
+        RistrettoScalar(self.0 + self.0)
+    }
+
+    fn invert(&self) -> CtOption<Self> {
+        CtOption::new(RistrettoScalar(self.0.invert()), !self.is_zero())
+    }
+
+    // l = 5 (mod 8), so Atkin's algorithm applies: with v = (2a)^((l - 5) / 8)
+    // and i = 2av^2, a square root of a is av(i - 1).
+    fn sqrt(&self) -> CtOption<Self> {
+        let v = self.double().pow_vartime(L_MINUS_5_OVER_8);
+        let i = self.double() * v.square();
+        let root = *self * v * (i - Self::one());
+        CtOption::new(root, root.square().ct_eq(self))
+    }
+}
+
+// (l - 5) / 8 as little-endian 64-bit limbs.
+const L_MINUS_5_OVER_8: [u64; 4] = [0xcb02_4c63_4b9e_ba7d, 0x029b_df3b_d45e_f39a, 0, 0x0200_0000_0000_0000];
+
+impl PrimeField for RistrettoScalar {
+    type Repr = [u8; 32];
+
+    /// Accepts only the canonical little-endian encoding.
+    fn from_repr(repr: [u8; 32]) -> CtOption<Self> {
+        let scalar = Scalar::from_bits(repr);
+        CtOption::new(RistrettoScalar(scalar), Choice::from(scalar.is_canonical() as u8))
+    }
+
+    fn to_repr(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    fn is_odd(&self) -> Choice {
+        Choice::from(self.0.as_bytes()[0] & 1)
+    }
+
+    const NUM_BITS: u32 = 253;
+    const CAPACITY: u32 = 252;
+    const S: u32 = 2;
+
+    /// 2 generates the multiplicative group: `l - 1 = 2^2 * 3 * 11 * p_1 * p_2`
+    /// for two large primes, and 2 to the power of `(l - 1) / q` is not one for
+    /// any of these prime factors `q`.
+    fn multiplicative_generator() -> Self {
+        RistrettoScalar::from(2)
+    }
+
+    /// `2^((l - 1) / 4)`, a square root of -1.
+    fn root_of_unity() -> Self {
+        RistrettoScalar(Scalar::from_bits([
+            212, 7, 190, 235, 223, 117, 135, 190, 254, 131, 206, 66, 83, 86, 240, 14,
+            122, 194, 193, 171, 96, 109, 61, 125, 231, 129, 121, 224, 16, 115, 74, 9,
+        ]))
+    }
+}
+
+/// Little-endian, as `Scalar::from_bytes_mod_order_wide`.
+impl WideReduce for RistrettoScalar {
+    fn from_wide(bytes: &[u8; 64]) -> Self {
+        RistrettoScalar(Scalar::from_bytes_mod_order_wide(bytes))
+    }
+}
+
+/// Views `scalars` as `RistrettoScalar`s without copying, for the Ristretto
+/// schemes built on the generic ones.
+pub(crate) fn to_ristretto_scalars(scalars: &[Scalar]) -> &[RistrettoScalar] {
+    // `RistrettoScalar` is a `#[repr(transparent)]` wrapper of `Scalar`.
+    unsafe { std::slice::from_raw_parts(scalars.as_ptr() as *const RistrettoScalar, scalars.len()) }
+}
+
+/// Unwraps `scalars` in place, reusing their allocation.
+pub(crate) fn from_ristretto_scalars(scalars: Vec<RistrettoScalar>) -> Vec<Scalar> {
+    let mut scalars = ManuallyDrop::new(scalars);
+    // Same layout as above, so the allocation can change hands.
+    unsafe { Vec::from_raw_parts(scalars.as_mut_ptr() as *mut Scalar, scalars.len(), scalars.capacity()) }
+}
+
+impl Sum for Ristretto {
+    fn sum<I: Iterator<Item = Ristretto>>(iter: I) -> Self {
+        Ristretto(iter.map(|point| point.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Ristretto> for Ristretto {
+    fn sum<I: Iterator<Item = &'a Ristretto>>(iter: I) -> Self {
+        Ristretto(iter.map(|point| point.0).sum())
+    }
+}
+
+impl Group for Ristretto {
+    type Scalar = RistrettoScalar;
+
+    fn random(mut rng: impl RngCore) -> Self {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        Ristretto(RistrettoPoint::from_uniform_bytes(&bytes))
+    }
+
+    fn identity() -> Self {
+        Ristretto(RistrettoPoint::identity())
+    }
+
+    fn generator() -> Self {
+        Ristretto(RISTRETTO_BASEPOINT_POINT)
+    }
+
+    fn is_identity(&self) -> Choice {
+        self.0.ct_eq(&RistrettoPoint::identity())
+    }
+
+    fn double(&self) -> Self {
+        Ristretto(self.0 + self.0)
+    }
+}
+
+impl GroupEncoding for Ristretto {
+    type Repr = [u8; 32];
+
+    fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        let point = CompressedRistretto(*bytes).decompress();
+        CtOption::new(Ristretto(point.unwrap_or_default()), Choice::from(point.is_some() as u8))
+    }
+
+    fn from_bytes_unchecked(bytes: &[u8; 32]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+
+    fn to_bytes(&self) -> [u8; 32] {
+        self.0.compress().to_bytes()
+    }
+}
+
+impl PrimeGroup for Ristretto {}
@@ -0,0 +1,169 @@
+//! LR-Schnorr over any `LrGroup`.
+
+use ff::PrimeField;
+use rand::{CryptoRng, RngCore, thread_rng};
+use std::marker::PhantomData;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{ByteEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_prime_field, sample_prime_field_lambda_with_rng, hash_tilde_prime_field, prime_field_list_to_bytes};
+use super::{LrGroup, hash_to_scalar, scalars_from_bytes, scalars_to_bytes, validate_public_key};
+
+pub use super::{SigningKey, VerifyingKey};
+
+pub fn keygen<G: LrGroup>(n: usize) -> (Vec<G::Scalar>, G) {
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<G: LrGroup, R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<G::Scalar>, G) {
+    keygen_inner(&Params::new(n), rng)
+}
+
+pub fn sign<G: LrGroup>(pk: &G, sk: &[G::Scalar], msg: &[u8]) -> (G::Scalar, G::Scalar) {
+    sign_with_rng(pk, sk, msg, &mut thread_rng())
+}
+
+pub fn sign_with_rng<G: LrGroup, R: RngCore + CryptoRng>(pk: &G, sk: &[G::Scalar], msg: &[u8], rng: &mut R) -> (G::Scalar, G::Scalar) {
+    let (_, c, z) = sign_inner(&expand_inner::<G>(HashTildeVersion::default(), sk), pk, msg, rng);
+    (c, z)
+}
+
+pub fn verify<G: LrGroup>(pk: &G, msg: &[u8], signature: &(G::Scalar, G::Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify<G: LrGroup>(pk: &G, msg: &[u8], signature: &(G::Scalar, G::Scalar)) -> Result<(), Error> {
+    let (c, z) = signature;
+    validate_public_key(pk)?;
+    if c == &challenge(pk, msg, &(G::generator() * z - *pk * c)) {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+// The message is hashed to a scalar first, as the Ristretto modules do.
+pub(crate) fn challenge<G: LrGroup>(pk: &G, msg: &[u8], point: &G) -> G::Scalar {
+    let msg_hash: G::Scalar = hash_to_scalar(&[msg]);
+    hash_to_scalar(&[pk.to_bytes().as_ref(), msg_hash.to_repr().as_ref(), point.to_bytes().as_ref()])
+}
+
+pub(crate) fn keygen_inner<G: LrGroup, R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<G::Scalar>, G) {
+    assert_nonempty(params.n);
+    let sk = sample_prime_field_lambda_with_rng(params.n, rng);
+    let pk = public_key_inner::<G>(params.hash_version, &sk);
+    (sk, pk)
+}
+
+pub(crate) fn expand_inner<G: LrGroup>(version: HashTildeVersion, sk: &[G::Scalar]) -> ExpandedSigningKey<G> {
+    assert_nonempty(sk.len());
+    let n = sk.len();
+    let a_list = Zeroizing::new(hash_tilde_prime_field(&prime_field_list_to_bytes(sk), n, false, version));
+    ExpandedSigningKey { n, version, x: aggregate_prime_field(&a_list, sk) }
+}
+
+pub(crate) fn public_key_inner<G: LrGroup>(version: HashTildeVersion, sk: &[G::Scalar]) -> G {
+    G::generator() * expand_inner::<G>(version, sk).x
+}
+
+/// The nonce `<b, k>` of a fresh nonce vector `k`.
+pub(crate) fn nonce<G: LrGroup, R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, rng: &mut R) -> Zeroizing<G::Scalar> {
+    let (n, version) = (esk.n, esk.version);
+    let k_list = Zeroizing::new(sample_prime_field_lambda_with_rng::<G::Scalar, R>(n, rng));
+    let b_list = Zeroizing::new(hash_tilde_prime_field(&prime_field_list_to_bytes(&k_list), n, true, version));
+    Zeroizing::new(aggregate_prime_field(&b_list, &k_list))
+}
+
+/// Returns the commitment along with `(c, z)`.
+pub(crate) fn sign_inner<G: LrGroup, R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, pk: &G, msg: &[u8], rng: &mut R) -> (G, G::Scalar, G::Scalar) {
+    let k = nonce(esk, rng);
+    let point = G::generator() * *k;
+    let c = challenge(pk, msg, &point);
+    (point, c, *k + c * esk.x)
+}
+
+/// The aggregated secret `<a, sk>`, with the length and hash version used to
+/// derive nonces.
+#[derive(Clone)]
+pub struct ExpandedSigningKey<G: LrGroup> {
+    pub(crate) n: usize,
+    pub(crate) version: HashTildeVersion,
+    pub(crate) x: G::Scalar,
+}
+
+impl<G: LrGroup> std::fmt::Debug for ExpandedSigningKey<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ExpandedSigningKey<{}>(n = {})", G::NAME, self.n)
+    }
+}
+
+impl<G: LrGroup> Drop for ExpandedSigningKey<G> {
+    fn drop(&mut self) {
+        self.x.zeroize();
+    }
+}
+
+/// `c || z`, two scalars in their `to_repr` form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature<G: LrGroup> {
+    pub c: G::Scalar,
+    pub z: G::Scalar,
+}
+
+impl<G: LrGroup> From<(G::Scalar, G::Scalar)> for Signature<G> {
+    fn from((c, z): (G::Scalar, G::Scalar)) -> Self {
+        Signature { c, z }
+    }
+}
+
+impl<G: LrGroup> From<Signature<G>> for (G::Scalar, G::Scalar) {
+    fn from(signature: Signature<G>) -> Self {
+        (signature.c, signature.z)
+    }
+}
+
+impl<G: LrGroup> ByteEncoding for Signature<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalars_to_bytes(&[self.c, self.z])
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let scalars = scalars_from_bytes(bytes, 2)?;
+        Ok(Signature { c: scalars[0], z: scalars[1] })
+    }
+}
+
+impl_serde_via_bytes!(Signature<G: LrGroup>);
+
+pub struct Schnorr<G>(PhantomData<G>);
+
+impl<G: LrGroup> SignatureScheme for Schnorr<G> {
+    const NAME: &'static str = "Generic LR-Schnorr";
+
+    type Params = Params;
+    type SecretKey = SigningKey<G>;
+    type PublicKey = VerifyingKey<G>;
+    type Signature = Signature<G>;
+    type ExpandedSecretKey = ExpandedSigningKey<G>;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey<G>, VerifyingKey<G>) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey<G>) -> VerifyingKey<G> {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey<G>) -> ExpandedSigningKey<G> {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey<G>, pk: &VerifyingKey<G>, msg: &[u8], rng: &mut R) -> Result<Signature<G>, Error> {
+        let (_, c, z) = sign_inner(esk, &pk.0, msg, rng);
+        Ok(Signature { c, z })
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey<G>, msg: &[u8], signature: &Signature<G>) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
+}
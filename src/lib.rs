@@ -1,56 +1,539 @@
 use curve25519_dalek::scalar::Scalar as Scalar25519;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::traits::IsIdentity;
 use sha2::{Digest, Sha512};
-use bls12_381::G1Affine;
+use bls12_381::{G1Affine, G1Projective, G2Affine, G2Prepared, Gt, multi_miller_loop, pairing};
 use bls12_381::Scalar as Scalar381;
+use ff::PrimeField;
 use std::convert::TryInto;
 
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use rand::thread_rng;
+use zeroize::{Zeroize, Zeroizing};
+
+use generic::{RistrettoScalar, from_ristretto_scalars, to_ristretto_scalars};
 
 const LAM: u8 = 128;
 
-#[inline]
-pub fn hash_tilde_curve25519(m: &[u8], n: usize, variant: bool) -> Vec<Scalar25519> {
+/// Derivation used by `hash_tilde_*` to expand a secret vector into coefficients.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HashTildeVersion {
+    /// Original derivation with a one-byte counter. Coefficients repeat once
+    /// `n > 256`, so it is only kept to check keys generated before `V1`.
+    V0,
+    /// Domain-separated derivation with a length-prefixed 64-bit counter.
+    V1,
+    /// `V1` with BLS12-381 secret vectors encoded canonically instead of via
+    /// their `Debug` output. Identical to `V1` for curve25519 schemes.
+    #[default]
+    V2,
+}
+
+impl HashTildeVersion {
+    /// Every version, newest first.
+    pub const ALL: [HashTildeVersion; 3] = [HashTildeVersion::V2, HashTildeVersion::V1, HashTildeVersion::V0];
+
+    /// The byte that identifies the version in encodings that store it.
+    pub fn to_byte(self) -> u8 {
+        match self {
+            HashTildeVersion::V0 => 0,
+            HashTildeVersion::V1 => 1,
+            HashTildeVersion::V2 => 2,
+        }
+    }
+
+    /// Inverse of `to_byte`, failing with `Error::UnknownHashVersion`.
+    pub fn from_byte(byte: u8) -> Result<Self, Error> {
+        HashTildeVersion::ALL.iter().copied().find(|v| v.to_byte() == byte).ok_or(Error::UnknownHashVersion(byte))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    pub n: usize,
+    pub hash_version: HashTildeVersion,
+}
+
+impl Params {
+    pub fn new(n: usize) -> Self {
+        Params { n, hash_version: HashTildeVersion::default() }
+    }
+}
+
+/// Common interface implemented by every leakage-resilient scheme in this crate.
+///
+/// `sign` always receives the public key, even for schemes that do not hash it
+/// into the challenge, so that callers can stay generic over the scheme.
+pub trait SignatureScheme {
+    const NAME: &'static str;
+
+    type Params;
+    type SecretKey: SecretEncoding;
+    type PublicKey: ByteEncoding + Clone + PartialEq;
+    type Signature: ByteEncoding + Clone;
+    type ExpandedSecretKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Self::Params, rng: &mut R) -> (Self::SecretKey, Self::PublicKey);
+    fn public_key(params: &Self::Params, sk: &Self::SecretKey) -> Self::PublicKey;
+
+    /// Precomputes the aggregated secret(s), so that signing no longer re-hashes
+    /// the whole secret vector.
+    ///
+    /// The expanded key is a constant-size secret that stays in memory for as
+    /// long as it is kept, which gives up the leakage resilience of the vector
+    /// form. Signing with the `SecretKey` instead recomputes it for every
+    /// signature and drops it afterwards.
+    fn expand(params: &Self::Params, sk: &Self::SecretKey) -> Self::ExpandedSecretKey;
+
+    /// Makes a single signing attempt with an expanded key, reporting a degenerate nonce instead of retrying.
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &Self::ExpandedSecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Result<Self::Signature, Error>;
+    fn try_verify(params: &Self::Params, pk: &Self::PublicKey, msg: &[u8], signature: &Self::Signature) -> Result<(), Error>;
+
+    /// `try_sign_expanded_with_rng` with an unexpanded key.
+    fn try_sign_with_rng<R: RngCore + CryptoRng>(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Result<Self::Signature, Error> {
+        Self::try_sign_expanded_with_rng(&Self::expand(params, sk), pk, msg, rng)
+    }
+
+    fn keygen(params: &Self::Params) -> (Self::SecretKey, Self::PublicKey) {
+        Self::keygen_with_rng(params, &mut thread_rng())
+    }
+
+    fn try_sign(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Result<Self::Signature, Error> {
+        Self::try_sign_with_rng(params, sk, pk, msg, &mut thread_rng())
+    }
+
+    /// Signs, drawing a fresh nonce until it is not degenerate.
+    ///
+    /// Panics on any other error, or after `MAX_SIGN_ATTEMPTS` degenerate
+    /// nonces in a row.
+    fn sign_with_rng<R: RngCore + CryptoRng>(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Self::Signature {
+        retry_degenerate(Self::NAME, || Self::try_sign_with_rng(params, sk, pk, msg, rng))
+    }
+
+    fn sign(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Self::Signature {
+        Self::sign_with_rng(params, sk, pk, msg, &mut thread_rng())
+    }
+
+    fn sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &Self::ExpandedSecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Self::Signature {
+        retry_degenerate(Self::NAME, || Self::try_sign_expanded_with_rng(esk, pk, msg, rng))
+    }
+
+    fn sign_expanded(esk: &Self::ExpandedSecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Self::Signature {
+        Self::sign_expanded_with_rng(esk, pk, msg, &mut thread_rng())
+    }
+
+    /// HMAC-DRBG seeded with the scheme name, the encoded key pair, `msg` and
+    /// `extra`, the nonce source of `sign_deterministic`. The public key
+    /// separates the nonces of one secret vector used under different
+    /// parameters, such as two `okamoto_multi` generator counts.
+    ///
+    /// To sign deterministically with an expanded key, pass the `nonce_rng`
+    /// of its secret key to `sign_expanded_with_rng`.
+    fn nonce_rng(sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8], extra: &[u8]) -> NonceRng {
+        let mut keys = sk.to_secret_bytes();
+        keys.extend_from_slice(&pk.to_bytes());
+        NonceRng::new(Self::NAME, &keys, msg, extra)
+    }
+
+    /// Signs with a nonce vector derived from `sk` and `msg`, as in RFC 6979,
+    /// so that the same inputs always give the same signature.
+    fn sign_deterministic(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Self::Signature {
+        Self::sign_with_rng(params, sk, pk, msg, &mut Self::nonce_rng(sk, pk, msg, &[]))
+    }
+
+    /// `sign_deterministic` with 32 bytes from `rng` mixed into the derivation.
+    fn sign_hedged_with_rng<R: RngCore + CryptoRng>(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Self::Signature {
+        let mut extra = Zeroizing::new([0u8; 32]);
+        rng.fill_bytes(&mut *extra);
+        Self::sign_with_rng(params, sk, pk, msg, &mut Self::nonce_rng(sk, pk, msg, &*extra))
+    }
+
+    fn sign_hedged(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Self::Signature {
+        Self::sign_hedged_with_rng(params, sk, pk, msg, &mut thread_rng())
+    }
+
+    /// Signs like `sign_with_rng`, but verifies the signature under `pk` before
+    /// returning it.
+    ///
+    /// A signature corrupted by a fault during signing can leak the aggregated
+    /// secret, so one that does not verify is dropped and `Error::FaultDetected`
+    /// returned instead. This costs a verification per signature. Unlike
+    /// `sign_with_rng` it never panics, and fails with `Error::DegenerateNonce`
+    /// after `MAX_SIGN_ATTEMPTS` degenerate nonces.
+    fn sign_hardened_with_rng<R: RngCore + CryptoRng>(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Result<Self::Signature, Error> {
+        let signature = try_retry_degenerate(|| Self::try_sign_with_rng(params, sk, pk, msg, rng))?;
+        Self::try_verify(params, pk, msg, &signature).map_err(|_| Error::FaultDetected)?;
+        Ok(signature)
+    }
+
+    fn sign_hardened(params: &Self::Params, sk: &Self::SecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Result<Self::Signature, Error> {
+        Self::sign_hardened_with_rng(params, sk, pk, msg, &mut thread_rng())
+    }
+
+    /// `sign_hardened_with_rng` with an expanded key.
+    fn sign_expanded_hardened_with_rng<R: RngCore + CryptoRng>(params: &Self::Params, esk: &Self::ExpandedSecretKey, pk: &Self::PublicKey, msg: &[u8], rng: &mut R) -> Result<Self::Signature, Error> {
+        let signature = try_retry_degenerate(|| Self::try_sign_expanded_with_rng(esk, pk, msg, rng))?;
+        Self::try_verify(params, pk, msg, &signature).map_err(|_| Error::FaultDetected)?;
+        Ok(signature)
+    }
+
+    fn sign_expanded_hardened(params: &Self::Params, esk: &Self::ExpandedSecretKey, pk: &Self::PublicKey, msg: &[u8]) -> Result<Self::Signature, Error> {
+        Self::sign_expanded_hardened_with_rng(params, esk, pk, msg, &mut thread_rng())
+    }
+
+    fn verify(params: &Self::Params, pk: &Self::PublicKey, msg: &[u8], signature: &Self::Signature) -> bool {
+        Self::try_verify(params, pk, msg, signature).is_ok()
+    }
+}
+
+/// Number of degenerate nonces after which the `sign` functions give up.
+///
+/// A valid key draws one with negligible probability, so hitting the limit
+/// means the RNG is broken or always returns the same nonce.
+pub const MAX_SIGN_ATTEMPTS: usize = 64;
+
+/// Retries `attempt` while it fails with `Error::DegenerateNonce`, and
+/// returns that error once `MAX_SIGN_ATTEMPTS` attempts failed.
+pub(crate) fn try_retry_degenerate<T>(mut attempt: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+    for _ in 0..MAX_SIGN_ATTEMPTS {
+        match attempt() {
+            Err(Error::DegenerateNonce) => continue,
+            result => return result,
+        }
+    }
+    Err(Error::DegenerateNonce)
+}
+
+/// `try_retry_degenerate` for the infallible `sign` functions, which panic
+/// instead.
+pub(crate) fn retry_degenerate<T>(scheme: &str, attempt: impl FnMut() -> Result<T, Error>) -> T {
+    match try_retry_degenerate(attempt) {
+        Ok(signature) => signature,
+        Err(Error::DegenerateNonce) => panic!("{} signing failed: {} degenerate nonces in a row", scheme, MAX_SIGN_ATTEMPTS),
+        Err(e) => panic!("{} signing failed: {}", scheme, e),
+    }
+}
+
+/// Finds the derivation a stored key pair was generated under.
+///
+/// Keys from before a derivation change keep working as long as they are used
+/// with the returned version in `Params::hash_version`.
+pub fn detect_hash_version<S>(n: usize, sk: &S::SecretKey, pk: &S::PublicKey) -> Option<HashTildeVersion>
+where
+    S: SignatureScheme<Params = Params>,
+{
+    HashTildeVersion::ALL.iter().copied().find(|&hash_version| {
+        S::public_key(&Params { n, hash_version }, sk) == *pk
+    })
+}
+
+/// Domain string from which the second LR-Okamoto generator is derived.
+pub const OKAMOTO_BASE_POINT2_DOMAIN: &[u8] = b"practical-lr-signature/okamoto/base-point-2/v1";
+
+lazy_static::lazy_static! {
+    static ref OKAMOTO_BASE_POINT2: RistrettoPoint = RistrettoPoint::hash_from_bytes::<Sha512>(OKAMOTO_BASE_POINT2_DOMAIN);
+    static ref BB3_G2_PREPARED: G2Prepared = G2Prepared::from(G2Affine::generator());
+    static ref BB3_GT: Gt = pairing(&G1Affine::generator(), &G2Affine::generator());
+}
+
+/// Generators of the LR-Okamoto schemes.
+///
+/// `g1` is the Ristretto basepoint and `g2` is hashed to the group from
+/// `OKAMOTO_BASE_POINT2_DOMAIN`, so nobody knows `log_g1(g2)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicParams {
+    pub g1: RistrettoPoint,
+    pub g2: RistrettoPoint,
+}
+
+impl PublicParams {
+    pub const BYTE_LEN: usize = 64;
+
+    pub fn standard() -> Self {
+        PublicParams { g1: RISTRETTO_BASEPOINT_POINT, g2: *OKAMOTO_BASE_POINT2 }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.g1.compress().as_bytes());
+        bytes[32..].copy_from_slice(self.g2.compress().as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        encoding::check_length(bytes, Self::BYTE_LEN)?;
+        let g1 = encoding::ristretto_from_bytes(&bytes[..32])?;
+        let g2 = encoding::ristretto_from_bytes(&bytes[32..])?;
+        Ok(PublicParams { g1, g2 })
+    }
+
+    /// Returns true if these are the generators this crate signs and verifies with.
+    pub fn is_standard(&self) -> bool {
+        *self == Self::standard()
+    }
+}
+
+const HASH_TILDE_DOMAIN: &[u8] = b"practical-lr-signature/hash-tilde/v1";
+
+fn hash_tilde_prefix(m: &[u8], variant: bool, version: HashTildeVersion) -> Sha512 {
     let mut hash = Sha512::default();
-    hash.update([variant as u8]);
+    match version {
+        HashTildeVersion::V0 => {
+            hash.update([variant as u8]);
+        }
+        HashTildeVersion::V1 | HashTildeVersion::V2 => {
+            hash.update([HASH_TILDE_DOMAIN.len() as u8]);
+            hash.update(HASH_TILDE_DOMAIN);
+            hash.update([variant as u8]);
+            hash.update((m.len() as u64).to_le_bytes());
+        }
+    }
     hash.update(m);
+    hash
+}
 
-    let mut result = Vec::with_capacity(n);
-    for i in 0..n {
-        let mut current = hash.clone();
-        current.update([i as u8]);
-        result.push(Scalar25519::from_hash(current));
+fn hash_tilde_counter(hash: &mut Sha512, i: usize, version: HashTildeVersion) {
+    match version {
+        HashTildeVersion::V0 => hash.update([i as u8]),
+        HashTildeVersion::V1 | HashTildeVersion::V2 => {
+            hash.update([8u8]);
+            hash.update((i as u64).to_le_bytes());
+        }
     }
-    result
+}
+
+// The `n` hashes shared by every `hash_tilde_*`, each mapped to a scalar by
+// `reduce`.
+fn hash_tilde_with<T, F: Fn(Sha512) -> T>(m: &[u8], n: usize, variant: bool, version: HashTildeVersion, reduce: F) -> Vec<T> {
+    let hash = hash_tilde_prefix(m, variant, version);
+    (0..n).map(|i| {
+        let mut current = hash.clone();
+        hash_tilde_counter(&mut current, i, version);
+        reduce(current)
+    }).collect()
+}
+
+#[inline]
+pub fn hash_tilde_curve25519(m: &[u8], n: usize, variant: bool, version: HashTildeVersion) -> Vec<Scalar25519> {
+    from_ristretto_scalars(hash_tilde_prime_field::<RistrettoScalar>(m, n, variant, version))
 }
 
 #[inline]
 pub fn sample_curve25519_lambda(n: usize) -> Vec<Scalar25519> {
-    let mut rng = thread_rng();
-    (0..n).map(|_| Scalar25519::from(rng.gen_range(0, LAM))).collect()
+    sample_curve25519_lambda_with_rng(n, &mut thread_rng())
+}
+
+#[inline]
+pub fn sample_curve25519_lambda_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> Vec<Scalar25519> {
+    from_ristretto_scalars(sample_prime_field_lambda_with_rng::<RistrettoScalar, R>(n, rng))
 }
 
 #[inline]
 pub fn aggregate_curve25519(a: &[Scalar25519], b: &[Scalar25519]) -> Scalar25519 {
+    aggregate_prime_field(to_ristretto_scalars(a), to_ristretto_scalars(b)).0
+}
+
+/// Adds to `share` a random vector orthogonal to `coefficients`, so that
+/// `aggregate_curve25519(coefficients, share)` is unchanged.
+///
+/// Panics if `n < 2`, where the only such vector is zero.
+#[inline]
+pub fn refresh_share_curve25519<R: RngCore + CryptoRng>(share: &[Scalar25519], coefficients: &[Scalar25519], rng: &mut R) -> Vec<Scalar25519> {
+    from_ristretto_scalars(refresh_share_prime_field(to_ristretto_scalars(share), to_ristretto_scalars(coefficients), rng))
+}
+
+#[inline]
+pub fn curve25519_scalar_list_to_bytes(a: &[Scalar25519]) -> Zeroizing<Vec<u8>> {
+    prime_field_list_to_bytes(to_ristretto_scalars(a))
+}
+
+/// A prime field that reduces 64 uniform bytes to a nearly uniform element,
+/// the way its own crate hashes to scalars.
+pub trait WideReduce: PrimeField {
+    fn from_wide(bytes: &[u8; 64]) -> Self;
+}
+
+/// Little-endian, as `Scalar::from_bytes_wide`.
+impl WideReduce for Scalar381 {
+    fn from_wide(bytes: &[u8; 64]) -> Self {
+        Scalar381::from_bytes_wide(bytes)
+    }
+}
+
+/// Big-endian, as SEC 1 reads integers.
+impl WideReduce for k256::Scalar {
+    fn from_wide(bytes: &[u8; 64]) -> Self {
+        prime_field_from_wide(bytes)
+    }
+}
+
+/// Big-endian, as SEC 1 reads integers.
+impl WideReduce for p256::Scalar {
+    fn from_wide(bytes: &[u8; 64]) -> Self {
+        prime_field_from_wide(bytes)
+    }
+}
+
+/// Maps every 64-byte digest into `F` with `WideReduce`. On Ristretto and
+/// BLS12-381 this is the little-endian reduction of `hash_tilde_curve25519`
+/// and `hash_tilde_bls12_381`, which are built on it.
+#[inline]
+pub fn hash_tilde_prime_field<F: WideReduce>(m: &[u8], n: usize, variant: bool, version: HashTildeVersion) -> Vec<F> {
+    hash_tilde_with(m, n, variant, version, |hash| F::from_wide(hash.finalize().as_slice().try_into().unwrap()))
+}
+
+/// Reads `bytes`, a multiple of 8 long, as a big-endian integer modulo the
+/// field order.
+pub(crate) fn prime_field_from_wide<F: PrimeField>(bytes: &[u8]) -> F {
+    let base = F::from(u64::MAX) + F::one();
+    bytes.chunks(8).fold(F::zero(), |acc, limb| acc * base + F::from(u64::from_be_bytes(limb.try_into().unwrap())))
+}
+
+#[inline]
+pub fn sample_prime_field_lambda_with_rng<F: PrimeField, R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> Vec<F> {
+    (0..n).map(|_| F::from(rng.gen_range(0, LAM) as u64)).collect()
+}
+
+#[inline]
+pub fn aggregate_prime_field<F: PrimeField>(a: &[F], b: &[F]) -> F {
     let n = a.len();
     assert_eq!(n, b.len());
-    (0..n).fold(Scalar25519::zero(), |sum, i| sum + a[i] * b[i])
+    let sum = a.iter().zip(b).fold(F::zero(), |sum, (a, b)| sum + *a * b);
+    #[cfg(test)]
+    let sum = if fault::hit() { sum + F::one() } else { sum };
+    sum
 }
 
+/// `refresh_share_curve25519` for any prime field.
 #[inline]
-pub fn curve25519_scalar_list_to_bytes(a: &[Scalar25519]) -> Vec<u8> {
-    bincode::serialize(&a).unwrap()
+pub fn refresh_share_prime_field<F: WideReduce + Zeroize, R: RngCore + CryptoRng>(share: &[F], coefficients: &[F], rng: &mut R) -> Vec<F> {
+    let n = share.len();
+    assert_eq!(n, coefficients.len());
+    assert!(n >= 2, "a share of length {} cannot be refreshed", n);
+    let pivot = coefficients.iter().rposition(|a| !bool::from(a.is_zero())).expect("all coefficients are zero");
+    let mut delta: Zeroizing<Vec<F>> = Zeroizing::new((0..n).map(|_| {
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        rng.fill_bytes(&mut *bytes);
+        F::from_wide(&bytes)
+    }).collect());
+    delta[pivot] = F::zero();
+    delta[pivot] = -aggregate_prime_field(coefficients, &delta) * coefficients[pivot].invert().unwrap();
+    share.iter().zip(delta.iter()).map(|(s, d)| *s + d).collect()
+}
+
+/// Encodes a secret vector as the input of `hash_tilde_prime_field`, as a
+/// little-endian `u64` length followed by the canonical `to_repr` of every
+/// element. Every `HashTildeVersion` uses this encoding.
+#[inline]
+pub fn prime_field_list_to_bytes<F: PrimeField>(a: &[F]) -> Zeroizing<Vec<u8>> {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(8 + 32 * a.len()));
+    bytes.extend_from_slice(&(a.len() as u64).to_le_bytes());
+    for element in a {
+        bytes.extend_from_slice(element.to_repr().as_ref());
+    }
+    bytes
 }
 
 #[inline]
 pub fn hash_scalar_bls12_381(msg: &[u8]) -> Scalar381 {
     let mut msg_hash = Sha512::default();
-    msg_hash.update(&msg);
+    msg_hash.update(msg);
     Scalar381::from_bytes_wide(&msg_hash.finalize().as_slice().try_into().unwrap())
 }
 
+/// Checks the LR-BB3 equation `e(s, u + g2^H(m) + v^r) == e(g1, g2)`,
+/// rearranged as `e(s, u) * e(s^H(m), g2) * e(s^r, v)` so that `u` and `v`
+/// can be prepared once and the pairings share one final exponentiation.
+pub(crate) fn bb3_verify_prepared(u: &G2Prepared, v: &G2Prepared, msg: &[u8], s: &G1Affine, r: &Scalar381) -> Result<(), Error> {
+    let msg_hash = hash_scalar_bls12_381(msg);
+    let s_projective = G1Projective::from(s);
+    let mut points = [G1Affine::identity(); 2];
+    G1Projective::batch_normalize(&[s_projective * msg_hash, s_projective * r], &mut points);
+    let terms = [(s, u), (&points[0], &*BB3_G2_PREPARED), (&points[1], v)];
+    if multi_miller_loop(&terms).final_exponentiation() == *BB3_GT {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+/// Rejects a secret vector of length zero, whose aggregate is always zero.
+pub(crate) fn validate_nonempty<T>(sk: &[T]) -> Result<(), Error> {
+    if sk.is_empty() {
+        return Err(Error::EmptySecretKey);
+    }
+    Ok(())
+}
+
+/// `validate_nonempty` for key generation and expansion, which cannot fail.
+pub(crate) fn assert_nonempty(n: usize) {
+    assert!(n > 0, "{}", Error::EmptySecretKey);
+}
+
+/// Rejects the identity, which any signature over a zero aggregate would
+/// verify under.
+pub(crate) fn validate_ristretto_public_key(pk: &RistrettoPoint) -> Result<(), Error> {
+    if pk.is_identity() {
+        return Err(Error::IdentityPublicKey);
+    }
+    Ok(())
+}
+
+/// Rejects scalars that are not reduced modulo the group order. Decoding
+/// already does, but the raw tuple API takes `Scalar`s built any way.
+pub(crate) fn validate_canonical(scalars: &[&Scalar25519]) -> Result<(), Error> {
+    if scalars.iter().all(|scalar| scalar.is_canonical()) {
+        Ok(())
+    } else {
+        Err(Error::NonCanonicalScalar)
+    }
+}
+
+/// Rejects the identity and points outside the prime-order subgroup of G2.
+/// Decoding checks the subgroup, but `G2Affine`s built without it do not.
+pub(crate) fn validate_g2_public_key(pk: &G2Affine) -> Result<(), Error> {
+    if bool::from(pk.is_identity()) {
+        return Err(Error::IdentityPublicKey);
+    }
+    if !bool::from(pk.is_on_curve() & pk.is_torsion_free()) {
+        return Err(Error::MalformedPoint);
+    }
+    Ok(())
+}
+
+/// Rejects the identity and points outside the prime-order subgroup of G1.
+pub(crate) fn validate_g1_signature(signature: &G1Affine) -> Result<(), Error> {
+    if bool::from(signature.is_identity()) {
+        return Err(Error::SignatureInvalid);
+    }
+    if !bool::from(signature.is_on_curve() & signature.is_torsion_free()) {
+        return Err(Error::MalformedPoint);
+    }
+    Ok(())
+}
+
 #[inline]
 pub fn hash_g1_bls12_381(msg: &[u8]) -> G1Affine {
+    hash_g1_bls12_381_with_domain(msg, bls::DEFAULT_DST)
+}
+
+/// RFC 9380 `hash_to_curve` for the suite BLS12381G1_XMD:SHA-256_SSWU_RO_.
+#[inline]
+pub fn hash_g1_bls12_381_with_domain(msg: &[u8], domain: &[u8]) -> G1Affine {
+    use bls12_381::G1Projective;
+    use bls12_381::hash_to_curve::{HashToCurve, ExpandMsgXmd};
+
+    let g = <G1Projective as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(
+        msg, domain,
+    );
+    G1Affine::from(g)
+}
+
+/// The non-uniform `encode_to_curve` map under the tag `"test_domain"`, which
+/// `hash_g1_bls12_381` used before it switched to `hash_to_curve`.
+#[inline]
+pub fn encode_g1_bls12_381_legacy(msg: &[u8]) -> G1Affine {
     use bls12_381::G1Projective;
     use bls12_381::hash_to_curve::{HashToCurve, ExpandMsgXmd};
 
@@ -61,45 +544,322 @@ pub fn hash_g1_bls12_381(msg: &[u8]) -> G1Affine {
 }
 
 #[inline]
-pub fn hash_tilde_bls12_381(m: &[u8], n: usize, variant: bool) -> Vec<Scalar381> {
-    let mut hash = Sha512::default();
-    hash.update([variant as u8]);
-    hash.update(m);
-
-    let mut result = Vec::with_capacity(n);
-    for i in 0..n {
-        let mut current = hash.clone();
-        current.update([i as u8]);
-        let current: [u8; 64] = current.finalize().as_slice().try_into().unwrap();
-        result.push(Scalar381::from_bytes_wide(&current));
-    }
-    result
+pub fn hash_tilde_bls12_381(m: &[u8], n: usize, variant: bool, version: HashTildeVersion) -> Vec<Scalar381> {
+    hash_tilde_prime_field(m, n, variant, version)
 }
 
 #[inline]
 pub fn sample_bls12_381_lambda(n: usize) -> Vec<Scalar381> {
-    let mut rng = thread_rng();
-    (0..n).map(|_| Scalar381::from(rng.gen_range(0, LAM) as u64)).collect()
+    sample_bls12_381_lambda_with_rng(n, &mut thread_rng())
+}
+
+#[inline]
+pub fn sample_bls12_381_lambda_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> Vec<Scalar381> {
+    sample_prime_field_lambda_with_rng(n, rng)
 }
 
 #[inline]
 pub fn aggregate_bls12_381(a: &[Scalar381], b: &[Scalar381]) -> Scalar381 {
-    let n = a.len();
-    assert_eq!(n, b.len());
-    (0..n).fold(Scalar381::zero(), |sum, i| sum + a[i] * b[i])
+    aggregate_prime_field(a, b)
 }
 
+/// Adds to `share` a random vector orthogonal to `coefficients`, so that
+/// `aggregate_bls12_381(coefficients, share)` is unchanged.
+///
+/// Panics if `n < 2`, where the only such vector is zero.
 #[inline]
-pub fn bls12_381_scalar_list_to_bytes(a: &[Scalar381]) -> Vec<u8> {
-    let a_repr = format!{"{:?}", a};
-    Vec::from(a_repr.as_bytes())
+pub fn refresh_share_bls12_381<R: RngCore + CryptoRng>(share: &[Scalar381], coefficients: &[Scalar381], rng: &mut R) -> Vec<Scalar381> {
+    refresh_share_prime_field(share, coefficients, rng)
+}
+
+/// Encodes a secret vector as the input of `hash_tilde_bls12_381`.
+///
+/// From `V2` on this is a little-endian `u64` length followed by the 32-byte
+/// little-endian encoding of every scalar. Older versions hash the `Debug`
+/// output, which is only kept so existing keys can still be used.
+#[inline]
+pub fn bls12_381_scalar_list_to_bytes(a: &[Scalar381], version: HashTildeVersion) -> Zeroizing<Vec<u8>> {
+    match version {
+        HashTildeVersion::V0 | HashTildeVersion::V1 => {
+            let a_repr = Zeroizing::new(format!{"{:?}", a});
+            Zeroizing::new(Vec::from(a_repr.as_bytes()))
+        }
+        HashTildeVersion::V2 => prime_field_list_to_bytes(a),
+    }
+}
+
+
+/// Fault injection for testing hardened signing.
+///
+/// The aggregations and the BB3 inversion call `hit`, and the computation that
+/// `inject` selects is corrupted.
+#[cfg(test)]
+pub(crate) mod fault {
+    use std::cell::Cell;
+
+    thread_local! {
+        static COUNTDOWN: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    /// Corrupts the `skip + 1`-th faultable computation on this thread.
+    pub(crate) fn inject(skip: usize) {
+        COUNTDOWN.with(|countdown| countdown.set(Some(skip)));
+    }
+
+    pub(crate) fn clear() {
+        COUNTDOWN.with(|countdown| countdown.set(None));
+    }
+
+    pub(crate) fn hit() -> bool {
+        COUNTDOWN.with(|countdown| match countdown.get() {
+            Some(0) => {
+                countdown.set(None);
+                true
+            }
+            Some(skip) => {
+                countdown.set(Some(skip - 1));
+                false
+            }
+            None => false,
+        })
+    }
 }
 
+mod batch;
+#[macro_use]
+mod encoding;
+mod error;
+mod mta;
+mod nonce;
+mod secret;
+
+pub use encoding::{ByteEncoding, SecretEncoding};
+pub use error::Error;
+pub use nonce::NonceRng;
 
 pub mod bls;
 pub mod bb3_aim;
 pub mod bb3_cml;
 pub mod ecdsa;
+pub mod generic;
+pub mod ecdsa_weierstrass;
+pub mod ecdsa_p256;
+pub mod ecdsa_secp256k1;
 pub mod schnorr;
 pub mod okamoto_aim;
-pub mod okamoto_cml;
\ No newline at end of file
+pub mod okamoto_cml;
+pub mod okamoto_multi;
+pub mod params;
+
+pub use bls::Bls;
+pub use bb3_aim::Bb3Aim;
+pub use bb3_cml::Bb3Cml;
+pub use ecdsa::Ecdsa;
+pub use ecdsa_p256::EcdsaP256;
+pub use ecdsa_secp256k1::EcdsaSecp256k1;
+pub use schnorr::Schnorr;
+pub use okamoto_aim::OkamotoAim;
+pub use okamoto_cml::OkamotoCml;
+pub use okamoto_multi::OkamotoMulti;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip<S: SignatureScheme<Params = Params>>()
+    where
+        S::SecretKey: PartialEq + std::fmt::Debug + serde::Serialize + serde::de::DeserializeOwned,
+        S::PublicKey: PartialEq + std::fmt::Debug + serde::Serialize + serde::de::DeserializeOwned,
+        S::Signature: PartialEq + std::fmt::Debug + serde::Serialize + serde::de::DeserializeOwned,
+    {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let params = Params::new(100);
+        let msg = "Hello, world!".as_bytes();
+        let (sk, pk) = S::keygen(&params);
+        let signature = S::sign(&params, &sk, &pk, msg);
+        assert!(S::verify(&params, &pk, msg, &signature), "{} failed to verify", S::NAME);
+        assert!(!S::verify(&params, &pk, "Goodbye, world!".as_bytes(), &signature), "{} accepted a wrong message", S::NAME);
+
+        let esk = S::expand(&params, &sk);
+        let signature = S::sign_expanded(&esk, &pk, msg);
+        assert!(S::verify(&params, &pk, msg, &signature), "{} failed to verify with an expanded key", S::NAME);
+
+        let seeded = || {
+            let mut rng = ChaCha20Rng::from_seed([7u8; 32]);
+            let (sk, pk) = S::keygen_with_rng(&params, &mut rng);
+            let signature = S::sign_with_rng(&params, &sk, &pk, msg, &mut rng);
+            (sk, pk, signature)
+        };
+        assert_eq!(seeded(), seeded(), "{} is not reproducible", S::NAME);
+
+        let sk_bytes = sk.to_secret_bytes();
+        let pk_bytes = pk.to_bytes();
+        let signature_bytes = signature.to_bytes();
+        assert_eq!(S::SecretKey::from_bytes(&sk_bytes).unwrap(), sk);
+        assert_eq!(S::PublicKey::from_bytes(&pk_bytes).unwrap(), pk);
+        assert_eq!(S::Signature::from_bytes(&signature_bytes).unwrap(), signature);
+        assert!(matches!(S::SecretKey::from_bytes(&sk_bytes[..sk_bytes.len() - 1]), Err(Error::LengthMismatch { .. })));
+        assert_eq!(S::PublicKey::from_bytes(&pk_bytes[1..]).err(), Some(Error::LengthMismatch { expected: pk_bytes.len(), actual: pk_bytes.len() - 1 }));
+        assert_eq!(
+            S::Signature::from_bytes(&[signature_bytes.as_slice(), &[0]].concat()).err(),
+            Some(Error::LengthMismatch { expected: signature_bytes.len(), actual: signature_bytes.len() + 1 }),
+        );
+        let mut non_canonical = signature_bytes.clone();
+        let tail = non_canonical.len() - 32;
+        non_canonical[tail..].iter_mut().for_each(|b| *b = 0xff);
+        assert!(S::Signature::from_bytes(&non_canonical).is_err(), "{}", S::NAME);
+        assert_eq!(bincode::deserialize::<S::SecretKey>(&bincode::serialize(&sk).unwrap()).unwrap(), sk);
+        assert_eq!(bincode::deserialize::<S::PublicKey>(&bincode::serialize(&pk).unwrap()).unwrap(), pk);
+        assert_eq!(bincode::deserialize::<S::Signature>(&bincode::serialize(&signature).unwrap()).unwrap(), signature);
+
+        let signature = S::sign_deterministic(&params, &sk, &pk, msg);
+        assert!(S::verify(&params, &pk, msg, &signature), "{} failed to verify", S::NAME);
+        assert_eq!(S::sign_deterministic(&params, &sk, &pk, msg), signature, "{} is not deterministic", S::NAME);
+        assert_eq!(S::sign_expanded_with_rng(&esk, &pk, msg, &mut S::nonce_rng(&sk, &pk, msg, &[])), signature, "{}", S::NAME);
+        let hedged = S::sign_hedged(&params, &sk, &pk, msg);
+        assert!(S::verify(&params, &pk, msg, &hedged), "{} failed to verify", S::NAME);
+        // BLS signatures have no nonce to hedge.
+        if S::sign(&params, &sk, &pk, msg) != S::sign(&params, &sk, &pk, msg) {
+            assert_ne!(hedged, signature, "{} did not hedge", S::NAME);
+        }
+
+        assert!(S::sign_hardened(&params, &sk, &pk, msg).is_ok(), "{} failed to sign", S::NAME);
+        // The first faultable computation is the aggregation of the secret.
+        fault::inject(0);
+        let signature = S::sign(&params, &sk, &pk, msg);
+        assert!(!S::verify(&params, &pk, msg, &signature), "{} did not see the fault", S::NAME);
+        let mut detected = 0;
+        for skip in 0..6 {
+            for expanded in [false, true].iter() {
+                fault::inject(skip);
+                let result = if *expanded {
+                    S::sign_expanded_hardened(&params, &esk, &pk, msg)
+                } else {
+                    S::sign_hardened(&params, &sk, &pk, msg)
+                };
+                fault::clear();
+                match result {
+                    Ok(signature) => assert!(S::verify(&params, &pk, msg, &signature), "{} released a faulty signature", S::NAME),
+                    Err(e) => {
+                        assert_eq!(e, Error::FaultDetected, "{}", S::NAME);
+                        detected += 1;
+                    }
+                }
+            }
+        }
+        assert!(detected > 0, "{} detected no fault", S::NAME);
+    }
+
+    #[test]
+    fn test_okamoto_public_params() {
+        let params = PublicParams::standard();
+        assert_eq!(params.g2, RistrettoPoint::hash_from_bytes::<Sha512>(OKAMOTO_BASE_POINT2_DOMAIN));
+        assert_ne!(params.g1, params.g2);
+        assert_eq!(params.g2.compress().to_bytes(), [
+            212, 191, 156, 252, 238, 19, 199, 38, 171, 27, 61, 73, 113, 166, 210, 89,
+            1, 55, 190, 222, 137, 78, 94, 62, 177, 172, 230, 10, 123, 97, 122, 66,
+        ]);
+        assert!(PublicParams::from_bytes(&params.to_bytes()).unwrap().is_standard());
+        assert_eq!(PublicParams::from_bytes(&params.to_bytes()[1..]), Err(Error::LengthMismatch { expected: 64, actual: 63 }));
+    }
+
+    fn all_distinct<T: PartialEq>(list: &[T]) -> bool {
+        (0..list.len()).all(|i| (i + 1..list.len()).all(|j| list[i] != list[j]))
+    }
+
+    #[test]
+    fn test_hash_tilde_distinct_for_large_n() {
+        let n = 2538;
+        let m = b"secret vector";
+        assert!(all_distinct(&hash_tilde_curve25519(m, n, false, HashTildeVersion::V1)));
+        assert!(all_distinct(&hash_tilde_bls12_381(m, n, true, HashTildeVersion::V1)));
+
+        let legacy = hash_tilde_curve25519(m, n, false, HashTildeVersion::V0);
+        assert_eq!(legacy[0], legacy[256]);
+    }
+
+    #[test]
+    fn test_hash_tilde_versions_are_separated() {
+        let m = b"secret vector";
+        let v0 = hash_tilde_curve25519(m, 4, false, HashTildeVersion::V0);
+        let v1 = hash_tilde_curve25519(m, 4, false, HashTildeVersion::V1);
+        assert!(v0.iter().all(|a| !v1.contains(a)));
+        assert_ne!(hash_tilde_curve25519(m, 4, true, HashTildeVersion::V1), v1);
+    }
+
+    #[test]
+    fn test_bls12_381_canonical_encoding() {
+        let a = [Scalar381::from(1u64), Scalar381::from(258u64)];
+        let bytes = bls12_381_scalar_list_to_bytes(&a, HashTildeVersion::V2);
+        assert_eq!(bytes.len(), 8 + 2 * 32);
+        assert_eq!(&bytes[..8], &2u64.to_le_bytes());
+        assert_eq!(bytes[8], 1);
+        assert!(bytes[9..40].iter().all(|&b| b == 0));
+        assert_eq!(&bytes[40..42], &[2, 1]);
+        assert!(bytes[42..].iter().all(|&b| b == 0));
+    }
+
+    // RFC 9380, appendix J.9.1.
+    #[test]
+    fn test_hash_g1_bls12_381_vectors() {
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let vectors: [(&[u8], &str, &str); 3] = [
+            (b"",
+             "052926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a1",
+             "08ba738453bfed09cb546dbb0783dbb3a5f1f566ed67bb6be0e8c67e2e81a4cc68ee29813bb7994998f3eae0c9c6a265"),
+            (b"abc",
+             "03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903",
+             "0b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d"),
+            (b"abcdef0123456789",
+             "11e0b079dea29a68f0383ee94fed1b940995272407e3bb916bbf268c263ddd57a6a27200a784cbc248e84f357ce82d98",
+             "03a87ae2caf14e8ee52e51fa2ed8eefe80f02457004ba4d486d6aa1f517c0889501dc7413753f9599b099ebcbbd2d709"),
+        ];
+        for (msg, x, y) in vectors.iter() {
+            let point = hash_g1_bls12_381_with_domain(msg, dst).to_uncompressed();
+            let hex: String = point.iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(hex, format!("{}{}", x, y));
+        }
+    }
+
+    #[test]
+    fn test_detect_hash_version() {
+        for &hash_version in HashTildeVersion::ALL.iter() {
+            let params = Params { n: 20, hash_version };
+            let (sk, pk) = Bls::keygen(&params);
+            assert_eq!(detect_hash_version::<Bls>(20, &sk, &pk), Some(hash_version));
+
+            let msg = "Hello, world!".as_bytes();
+            let signature = Bls::sign(&params, &sk, &pk, msg);
+            assert!(Bls::verify(&params, &pk, msg, &signature));
+        }
+        let (sk, _) = Bls::keygen(&Params::new(20));
+        let (_, pk) = Bls::keygen(&Params::new(20));
+        assert_eq!(detect_hash_version::<Bls>(20, &sk, &pk), None);
+    }
+
+    #[test]
+    fn test_prime_field_from_wide() {
+        let mut bytes = [0u8; 64];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = (i as u8).wrapping_mul(37).wrapping_add(0xa5);
+        }
+        let mut le = bytes;
+        le.reverse();
+        assert_eq!(prime_field_from_wide::<Scalar381>(&bytes), Scalar381::from_bytes_wide(&le));
+    }
+
+    #[test]
+    fn test_all_schemes() {
+        roundtrip::<Bls>();
+        roundtrip::<Bb3Aim>();
+        roundtrip::<Bb3Cml>();
+        roundtrip::<Ecdsa>();
+        roundtrip::<Schnorr>();
+        roundtrip::<OkamotoAim>();
+        roundtrip::<OkamotoCml>();
+        roundtrip::<EcdsaSecp256k1>();
+        roundtrip::<EcdsaP256>();
+    }
+}
\ No newline at end of file
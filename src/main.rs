@@ -1,3 +1,613 @@
+use std::fmt;
+use std::convert::TryFrom;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+use zeroize::Zeroizing;
+
+use practical_lr::params::{self, Curve, Leakage, Model};
+use practical_lr::{ByteEncoding, HashTildeVersion, Params, SecretEncoding, SignatureScheme};
+use practical_lr::{Bls, Bb3Aim, Bb3Cml, Ecdsa, EcdsaP256, EcdsaSecp256k1, Schnorr, OkamotoAim, OkamotoCml};
+
+const USAGE: &str = "\
+usage: practical-lr <command> [options]
+
+commands:
+  keygen   --scheme <scheme> --n <n> --secret-key <file> --public-key <file> [--hash-version <v0|v1|v2>] [--force]
+  sign     --secret-key <file> --signature <file> [--message <file>] [--scheme <scheme>] [--force]
+  verify   --public-key <file> --signature <file> [--message <file>] [--scheme <scheme>]
+  inspect  <file>
+  params   [--scheme <scheme>] [--security <bits>] [--n <n> | --leak-bits <bits> | --leak-rate <rate>]
+  sizes    [--scheme <scheme>] [--n <n>]
+
+schemes: bls, bb3_aim, bb3_cml, ecdsa, schnorr, okamoto_aim, okamoto_cml,
+         ecdsa_secp256k1, ecdsa_p256
+Messages are read from stdin when --message is omitted or is `-`.
+Output files are never replaced unless --force is given.";
+
+/// Every file starts with `MAGIC`, the scheme id, the kind of object it holds,
+/// the hash-tilde version and the vector length `n` (u64 LE) of the key pair,
+/// followed by the object's `ByteEncoding`.
+const MAGIC: &[u8; 4] = b"PLR1";
+const HEADER_LEN: usize = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scheme {
+    Bls,
+    Bb3Aim,
+    Bb3Cml,
+    Ecdsa,
+    Schnorr,
+    OkamotoAim,
+    OkamotoCml,
+    EcdsaSecp256k1,
+    EcdsaP256,
+}
+
+impl Scheme {
+    // Ids are positions in this list, so new schemes go at the end.
+    const ALL: [Scheme; 9] = [
+        Scheme::Bls, Scheme::Bb3Aim, Scheme::Bb3Cml, Scheme::Ecdsa, Scheme::Schnorr, Scheme::OkamotoAim, Scheme::OkamotoCml,
+        Scheme::EcdsaSecp256k1, Scheme::EcdsaP256,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Scheme::Bls => "bls",
+            Scheme::Bb3Aim => "bb3_aim",
+            Scheme::Bb3Cml => "bb3_cml",
+            Scheme::Ecdsa => "ecdsa",
+            Scheme::Schnorr => "schnorr",
+            Scheme::OkamotoAim => "okamoto_aim",
+            Scheme::OkamotoCml => "okamoto_cml",
+            Scheme::EcdsaSecp256k1 => "ecdsa_secp256k1",
+            Scheme::EcdsaP256 => "ecdsa_p256",
+        }
+    }
+
+    fn id(self) -> u8 {
+        Scheme::ALL.iter().position(|&s| s == self).unwrap() as u8 + 1
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Scheme::ALL.get((id as usize).checked_sub(1)?).copied()
+    }
+
+    fn from_name(name: &str) -> Result<Self, CliError> {
+        Scheme::ALL.iter().copied().find(|s| s.name() == name)
+            .ok_or_else(|| CliError::Usage(format!("unknown scheme `{}`", name)))
+    }
+
+    /// The curve and leakage model that `params` sizes the scheme's vector for.
+    fn leakage_model(self) -> (Curve, Model) {
+        match self {
+            Scheme::Bls | Scheme::Bb3Aim => (Curve::Bls12_381, Model::Aim),
+            Scheme::Bb3Cml => (Curve::Bls12_381, Model::Cml),
+            Scheme::Ecdsa | Scheme::Schnorr | Scheme::OkamotoAim => (Curve::Curve25519, Model::Aim),
+            Scheme::OkamotoCml => (Curve::Curve25519, Model::Cml),
+            Scheme::EcdsaSecp256k1 => (Curve::Secp256k1, Model::Aim),
+            Scheme::EcdsaP256 => (Curve::P256, Model::Aim),
+        }
+    }
+}
+
+trait SchemeOp {
+    type Output;
+    fn run<S: SignatureScheme<Params = Params>>(self) -> Self::Output;
+}
+
+fn dispatch<O: SchemeOp>(scheme: Scheme, op: O) -> O::Output {
+    match scheme {
+        Scheme::Bls => op.run::<Bls>(),
+        Scheme::Bb3Aim => op.run::<Bb3Aim>(),
+        Scheme::Bb3Cml => op.run::<Bb3Cml>(),
+        Scheme::Ecdsa => op.run::<Ecdsa>(),
+        Scheme::Schnorr => op.run::<Schnorr>(),
+        Scheme::OkamotoAim => op.run::<OkamotoAim>(),
+        Scheme::OkamotoCml => op.run::<OkamotoCml>(),
+        Scheme::EcdsaSecp256k1 => op.run::<EcdsaSecp256k1>(),
+        Scheme::EcdsaP256 => op.run::<EcdsaP256>(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    SecretKey,
+    PublicKey,
+    Signature,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::SecretKey => "secret key",
+            Kind::PublicKey => "public key",
+            Kind::Signature => "signature",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(String, io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            CliError::Io(path, e) => write!(f, "{}: {}", path, e),
+            CliError::Invalid(msg) => f.write_str(msg),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct File {
+    scheme: Scheme,
+    kind: Kind,
+    params: Params,
+    /// Secret keys pass through here, so the payload is wiped on drop.
+    payload: Zeroizing<Vec<u8>>,
+}
+
+fn version_from_name(name: &str) -> Result<HashTildeVersion, CliError> {
+    match name {
+        "v0" => Ok(HashTildeVersion::V0),
+        "v1" => Ok(HashTildeVersion::V1),
+        "v2" => Ok(HashTildeVersion::V2),
+        _ => Err(CliError::Usage(format!("unknown hash version `{}`", name))),
+    }
+}
+
+impl File {
+    fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let kind = match self.kind {
+            Kind::SecretKey => 0,
+            Kind::PublicKey => 1,
+            Kind::Signature => 2,
+        };
+        let mut bytes = Zeroizing::new(Vec::with_capacity(HEADER_LEN + self.payload.len()));
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[self.scheme.id(), kind, self.params.hash_version.to_byte()]);
+        bytes.extend_from_slice(&(self.params.n as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return None;
+        }
+        let scheme = Scheme::from_id(bytes[4])?;
+        let kind = match bytes[5] {
+            0 => Kind::SecretKey,
+            1 => Kind::PublicKey,
+            2 => Kind::Signature,
+            _ => return None,
+        };
+        let hash_version = HashTildeVersion::from_byte(bytes[6]).ok()?;
+        let n = read_n(&bytes[7..])?;
+        Some(File { scheme, kind, params: Params { n, hash_version }, payload: Zeroizing::new(bytes[HEADER_LEN..].to_vec()) })
+    }
+
+    fn read(path: &str, kind: Kind) -> Result<Self, CliError> {
+        let bytes = Zeroizing::new(fs::read(path).map_err(|e| CliError::Io(path.to_string(), e))?);
+        let file = File::from_bytes(&bytes)
+            .ok_or_else(|| CliError::Invalid(format!("{}: not a practical-lr file", path)))?;
+        if file.kind != kind {
+            return Err(CliError::Invalid(format!("{}: expected a {}, found a {}", path, kind.name(), file.kind.name())));
+        }
+        Ok(file)
+    }
+
+    /// Opens `path` for `write`, refusing to replace an existing file unless
+    /// `force` is set. Nothing is truncated yet, so a command can open all of
+    /// its outputs before it writes any of them.
+    fn create(path: &str, kind: Kind, force: bool) -> Result<Target<'_>, CliError> {
+        let created = !Path::new(path).exists();
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if force {
+            options.create(true);
+        } else {
+            options.create_new(true);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            if kind == Kind::SecretKey {
+                options.mode(0o600);
+            }
+        }
+        let file = options.open(path).map_err(|e| CliError::Io(path.to_string(), e))?;
+        Ok(Target { path, file, created })
+    }
+
+    /// Writes the file to `target`. Secret keys are readable by the owner only.
+    fn write(&self, target: Target) -> Result<(), CliError> {
+        let io_error = |e| CliError::Io(target.path.to_string(), e);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if self.kind == Kind::SecretKey {
+                // `mode` only applies to new files, so tighten a replaced one too.
+                target.file.set_permissions(fs::Permissions::from_mode(0o600)).map_err(io_error)?;
+            }
+        }
+        target.file.set_len(0).map_err(io_error)?;
+        (&target.file).write_all(&self.to_bytes()).map_err(io_error)
+    }
+}
+
+/// An output file opened by `File::create`.
+struct Target<'a> {
+    path: &'a str,
+    file: fs::File,
+    created: bool,
+}
+
+impl<'a> Target<'a> {
+    /// Gives up on the target, removing the file if `File::create` made it.
+    fn discard(self) {
+        drop(self.file);
+        if self.created {
+            fs::remove_file(self.path).ok();
+        }
+    }
+}
+
+fn decode<T: ByteEncoding>(file: &File, path: &str) -> Result<T, CliError> {
+    T::from_bytes(&file.payload).map_err(|e| CliError::Invalid(format!("{}: invalid {}: {}", path, file.kind.name(), e)))
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    /// Options that take no value.
+    const FLAGS: [&'static str; 1] = ["force"];
+
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if Args::FLAGS.contains(&name) {
+                    options.push((name.to_string(), String::new()));
+                    continue;
+                }
+                let value = iter.next().ok_or_else(|| CliError::Usage(format!("missing value for --{}", name)))?;
+                options.push((name.to_string(), value.clone()));
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args { positional, options })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.options.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.get(name).ok_or_else(|| CliError::Usage(format!("missing --{}", name)))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn parsed<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, CliError> {
+        self.get(name)
+            .map(|value| value.parse().map_err(|_| CliError::Usage(format!("invalid --{} `{}`", name, value))))
+            .transpose()
+    }
+
+    fn n(&self) -> Result<Option<usize>, CliError> {
+        match self.parsed("n")? {
+            Some(0) => Err(CliError::Usage("--n must be positive".to_string())),
+            n => Ok(n),
+        }
+    }
+
+    /// The scheme given with `--scheme`, or all of them.
+    fn schemes(&self) -> Result<Vec<Scheme>, CliError> {
+        Ok(match self.get("scheme") {
+            Some(name) => vec![Scheme::from_name(name)?],
+            None => Scheme::ALL.to_vec(),
+        })
+    }
+
+    fn check_scheme(&self, file: &File) -> Result<(), CliError> {
+        if let Some(name) = self.get("scheme") {
+            let scheme = Scheme::from_name(name)?;
+            if scheme != file.scheme {
+                return Err(CliError::Invalid(format!("file holds a {} {}, not {}", file.scheme.name(), file.kind.name(), name)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_message(path: Option<&str>) -> Result<Vec<u8>, CliError> {
+    match path {
+        None | Some("-") => {
+            let mut msg = Vec::new();
+            io::stdin().read_to_end(&mut msg).map_err(|e| CliError::Io("<stdin>".to_string(), e))?;
+            Ok(msg)
+        }
+        Some(path) => fs::read(path).map_err(|e| CliError::Io(path.to_string(), e)),
+    }
+}
+
+struct KeyGen {
+    params: Params,
+}
+
+impl SchemeOp for KeyGen {
+    type Output = (Zeroizing<Vec<u8>>, Vec<u8>);
+
+    fn run<S: SignatureScheme<Params = Params>>(self) -> Self::Output {
+        let (sk, pk) = S::keygen(&self.params);
+        (sk.to_secret_bytes(), pk.to_bytes())
+    }
+}
+
+struct Sign<'a> {
+    sk_file: &'a File,
+    sk_path: &'a str,
+    msg: &'a [u8],
+}
+
+impl<'a> SchemeOp for Sign<'a> {
+    type Output = Result<Vec<u8>, CliError>;
+
+    fn run<S: SignatureScheme<Params = Params>>(self) -> Self::Output {
+        let sk: S::SecretKey = decode(self.sk_file, self.sk_path)?;
+        let params = self.sk_file.params;
+        if read_n(&self.sk_file.payload) != Some(params.n) {
+            return Err(CliError::Invalid(format!("{}: key length does not match the header", self.sk_path)));
+        }
+        let pk = S::public_key(&params, &sk);
+        Ok(S::sign(&params, &sk, &pk, self.msg).to_bytes())
+    }
+}
+
+struct Verify<'a> {
+    pk_file: &'a File,
+    pk_path: &'a str,
+    sig_file: &'a File,
+    sig_path: &'a str,
+    msg: &'a [u8],
+}
+
+impl<'a> SchemeOp for Verify<'a> {
+    type Output = Result<(), CliError>;
+
+    fn run<S: SignatureScheme<Params = Params>>(self) -> Self::Output {
+        let pk: S::PublicKey = decode(self.pk_file, self.pk_path)?;
+        let signature: S::Signature = decode(self.sig_file, self.sig_path)?;
+        S::try_verify(&self.pk_file.params, &pk, self.msg, &signature).map_err(|e| CliError::Invalid(e.to_string()))
+    }
+}
+
+struct Sizes {
+    n: usize,
+}
+
+impl SchemeOp for Sizes {
+    type Output = (&'static str, usize, usize, usize);
+
+    fn run<S: SignatureScheme<Params = Params>>(self) -> Self::Output {
+        let params = Params::new(self.n);
+        let (sk, pk) = S::keygen(&params);
+        let signature = S::sign(&params, &sk, &pk, b"");
+        (S::NAME, sk.to_secret_bytes().len(), pk.to_bytes().len(), signature.to_bytes().len())
+    }
+}
+
+/// Reads a vector length `n`, as in the header and in front of every encoded
+/// secret key.
+fn read_n(bytes: &[u8]) -> Option<usize> {
+    let mut n = [0u8; 8];
+    n.copy_from_slice(bytes.get(..8)?);
+    usize::try_from(u64::from_le_bytes(n)).ok()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn run(args: &[String]) -> Result<i32, CliError> {
+    let (command, rest) = args.split_first().ok_or_else(|| CliError::Usage("missing command".to_string()))?;
+    let args = Args::parse(rest)?;
+    let mut stdout = io::stdout();
+
+    match command.as_str() {
+        "keygen" => {
+            let scheme = Scheme::from_name(args.required("scheme")?)?;
+            let n = args.n()?.ok_or_else(|| CliError::Usage("missing --n".to_string()))?;
+            let hash_version = args.get("hash-version").map(version_from_name).transpose()?.unwrap_or_default();
+            let params = Params { n, hash_version };
+            let sk_target = File::create(args.required("secret-key")?, Kind::SecretKey, args.flag("force"))?;
+            let pk_target = match args.required("public-key").and_then(|path| File::create(path, Kind::PublicKey, args.flag("force"))) {
+                Ok(target) => target,
+                Err(e) => {
+                    sk_target.discard();
+                    return Err(e);
+                }
+            };
+            let (sk, pk) = dispatch(scheme, KeyGen { params });
+            File { scheme, kind: Kind::SecretKey, params, payload: sk }.write(sk_target)?;
+            File { scheme, kind: Kind::PublicKey, params, payload: Zeroizing::new(pk) }.write(pk_target)?;
+        }
+        "sign" => {
+            let sk_path = args.required("secret-key")?;
+            let sk_file = File::read(sk_path, Kind::SecretKey)?;
+            args.check_scheme(&sk_file)?;
+            let msg = read_message(args.get("message"))?;
+            let signature = dispatch(sk_file.scheme, Sign { sk_file: &sk_file, sk_path, msg: &msg })?;
+            let file = File { scheme: sk_file.scheme, kind: Kind::Signature, params: sk_file.params, payload: Zeroizing::new(signature) };
+            file.write(File::create(args.required("signature")?, Kind::Signature, args.flag("force"))?)?;
+        }
+        "verify" => {
+            let pk_path = args.required("public-key")?;
+            let sig_path = args.required("signature")?;
+            let pk_file = File::read(pk_path, Kind::PublicKey)?;
+            let sig_file = File::read(sig_path, Kind::Signature)?;
+            args.check_scheme(&pk_file)?;
+            if sig_file.scheme != pk_file.scheme {
+                return Err(CliError::Invalid(format!("{} signature cannot be checked with a {} public key", sig_file.scheme.name(), pk_file.scheme.name())));
+            }
+            let msg = read_message(args.get("message"))?;
+            return match dispatch(pk_file.scheme, Verify { pk_file: &pk_file, pk_path, sig_file: &sig_file, sig_path, msg: &msg }) {
+                Ok(()) => {
+                    writeln!(stdout, "OK").ok();
+                    Ok(0)
+                }
+                Err(e) => {
+                    writeln!(stdout, "FAILED: {}", e).ok();
+                    Ok(1)
+                }
+            };
+        }
+        "inspect" => {
+            let path = args.positional.first().ok_or_else(|| CliError::Usage("missing file".to_string()))?;
+            let bytes = Zeroizing::new(fs::read(path).map_err(|e| CliError::Io(path.to_string(), e))?);
+            let file = File::from_bytes(&bytes)
+                .ok_or_else(|| CliError::Invalid(format!("{}: not a practical-lr file", path)))?;
+            writeln!(stdout, "scheme:       {}", file.scheme.name()).ok();
+            writeln!(stdout, "kind:         {}", file.kind.name()).ok();
+            writeln!(stdout, "hash version: {:?}", file.params.hash_version).ok();
+            writeln!(stdout, "n:            {}", file.params.n).ok();
+            if file.kind != Kind::SecretKey {
+                writeln!(stdout, "bytes:        {}", hex(&file.payload)).ok();
+            }
+            writeln!(stdout, "length:       {}", file.payload.len()).ok();
+        }
+        "params" => {
+            let security = args.parsed("security")?.unwrap_or(params::DEFAULT_SECURITY);
+            let n = args.n()?;
+            let leakage = match (args.parsed("leak-bits")?, args.parsed("leak-rate")?) {
+                (Some(_), Some(_)) => return Err(CliError::Usage("give at most one of --leak-bits and --leak-rate".to_string())),
+                (Some(bits), None) => Leakage::Bits(bits),
+                (None, Some(rate)) => Leakage::Rate(rate),
+                (None, None) => Leakage::Rate(0.0),
+            };
+            if n.is_some() && leakage != Leakage::Rate(0.0) {
+                return Err(CliError::Usage("--n cannot be combined with a leakage bound".to_string()));
+            }
+            if let Leakage::Rate(rate) = leakage {
+                if !(0.0..1.0).contains(&rate) {
+                    return Err(CliError::Usage(format!("--leak-rate must be in [0, 1), got {}", rate)));
+                }
+            }
+            match n {
+                Some(_) => writeln!(stdout, "{:<16} {:>6} {:>12} {:>12}", "scheme", "n", "leak bits", "leak rate").ok(),
+                None => writeln!(stdout, "{:<16} {:>6}", "scheme", "min n").ok(),
+            };
+            for scheme in args.schemes()? {
+                let (curve, model) = scheme.leakage_model();
+                match n {
+                    Some(n) => match (params::tolerated_bits(curve, model, security, n), params::tolerated_rate(curve, model, security, n)) {
+                        (Some(bits), Some(rate)) => writeln!(stdout, "{:<16} {:>6} {:>12} {:>12.4}", scheme.name(), n, bits, rate).ok(),
+                        _ => writeln!(stdout, "{:<16} {:>6} {:>12} {:>12}", scheme.name(), n, "too short", "-").ok(),
+                    },
+                    None => {
+                        let n = params::min_n(curve, model, security, leakage).expect("leakage rate checked above");
+                        writeln!(stdout, "{:<16} {:>6}", scheme.name(), n).ok()
+                    }
+                };
+            }
+        }
+        "sizes" => {
+            let n = args.n()?.unwrap_or(1);
+            let schemes = args.schemes()?;
+            writeln!(stdout, "{:<12} {:<16} {:>10} {:>10} {:>10}", "scheme", "name", "sk bytes", "pk bytes", "sig bytes").ok();
+            for scheme in schemes {
+                let (name, sk_len, pk_len, sig_len) = dispatch(scheme, Sizes { n });
+                writeln!(stdout, "{:<12} {:<16} {:>10} {:>10} {:>10}", scheme.name(), name, sk_len, pk_len, sig_len).ok();
+            }
+        }
+        "help" | "--help" | "-h" => {
+            writeln!(stdout, "{}", USAGE).ok();
+        }
+        _ => return Err(CliError::Usage(format!("unknown command `{}`", command))),
+    }
+    Ok(0)
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = match run(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    };
+    process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("practical-lr-cli-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let msg = path("msg");
+        fs::write(&msg, b"Hello, world!").unwrap();
+
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        for scheme in Scheme::ALL.iter() {
+            let path = |name: &str| path(&format!("{}.{}", scheme.name(), name));
+            let keygen = format!("keygen --scheme {} --n 20 --secret-key {} --public-key {}", scheme.name(), path("sk"), path("pk"));
+            assert_eq!(run(&args(&keygen)).unwrap(), 0);
+            let sign = format!("sign --secret-key {} --message {} --signature {}", path("sk"), msg, path("sig"));
+            assert_eq!(run(&args(&sign)).unwrap(), 0);
+            let verify = format!("verify --public-key {} --signature {} --message {}", path("pk"), path("sig"), msg);
+            assert_eq!(run(&args(&verify)).unwrap(), 0);
+            let verify_other = format!("verify --public-key {} --signature {} --message {}", path("pk"), path("sig"), path("sk"));
+            assert_eq!(run(&args(&verify_other)).unwrap(), 1);
+
+            let file = File::read(&path("sig"), Kind::Signature).unwrap();
+            assert_eq!(file.params, Params::new(20));
+            assert_eq!(File::from_bytes(&file.to_bytes()), Some(file));
+            assert!(File::read(&path("pk"), Kind::Signature).is_err());
+
+            assert!(matches!(run(&args(&keygen)), Err(CliError::Io(_, _))));
+            assert_eq!(run(&args(&format!("{} --force", keygen))).unwrap(), 0);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                assert_eq!(fs::metadata(path("sk")).unwrap().permissions().mode() & 0o777, 0o600);
+            }
+        }
+
+        // An unusable public key path is caught before the secret key is written.
+        let keygen = format!("keygen --scheme schnorr --n 20 --secret-key {} --public-key {}", path("new.sk"), path("missing/pk"));
+        assert!(matches!(run(&args(&keygen)), Err(CliError::Io(_, _))));
+        assert!(!Path::new(&path("new.sk")).exists());
+        let keygen = format!("keygen --scheme schnorr --n 20 --secret-key {} --public-key {}", path("schnorr.sk"), path("missing/pk"));
+        let sk = fs::read(path("schnorr.sk")).unwrap();
+        assert!(matches!(run(&args(&format!("{} --force", keygen))), Err(CliError::Io(_, _))));
+        assert_eq!(fs::read(path("schnorr.sk")).unwrap(), sk);
+        fs::remove_dir_all(&dir).unwrap();
+
+        for line in ["keygen --scheme bls --n 0 --secret-key sk --public-key pk", "params --n 0", "sizes --n 0", "params --leak-rate 1"].iter() {
+            assert!(matches!(run(&args(line)), Err(CliError::Usage(_))));
+        }
+        assert_eq!(run(&args("params --scheme schnorr --leak-rate 0.5")).unwrap(), 0);
+        assert_eq!(run(&args("params --n 20")).unwrap(), 0);
+    }
 }
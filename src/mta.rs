@@ -0,0 +1,144 @@
+//! Multiplicative-to-additive conversion of BLS12-381 scalars.
+//!
+//! The sender holds `a` and the receiver holds `b`. At the end they hold
+//! `alpha` and `beta` with `alpha + beta = a * b`, and neither learns the
+//! other's input. This is Gilboa's multiplication over one oblivious transfer
+//! per bit of `b`, using the Chou-Orlandi OT on Ristretto. It is secure
+//! against parties that follow the protocol, which is all two-party CML
+//! signing needs.
+
+use bls12_381::Scalar as Scalar381;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar as Scalar25519;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+use std::convert::TryInto;
+use zeroize::Zeroize;
+
+/// Bits of a BLS12-381 scalar, and so the number of transfers per conversion.
+pub(crate) const OT_COUNT: usize = 255;
+
+const MTA_DOMAIN: &[u8] = b"practical-lr mta pad";
+
+pub(crate) struct Sender {
+    input: Scalar381,
+    secret: Scalar25519,
+    key: RistrettoPoint,
+}
+
+impl Sender {
+    pub(crate) fn new<R: RngCore + CryptoRng>(input: Scalar381, rng: &mut R) -> Self {
+        let secret = Scalar25519::random(rng);
+        Sender { input, secret, key: &secret * &RISTRETTO_BASEPOINT_TABLE }
+    }
+
+    pub(crate) fn key(&self) -> RistrettoPoint {
+        self.key
+    }
+
+    /// Encrypts `(s_i, s_i + a * 2^i)` to the receiver's choices and returns
+    /// the sender's share `-sum s_i` with the ciphertexts.
+    pub(crate) fn respond<R: RngCore + CryptoRng>(&self, choices: &[RistrettoPoint], rng: &mut R) -> (Scalar381, Vec<[Scalar381; 2]>) {
+        assert_eq!(choices.len(), OT_COUNT);
+        let mut share = Scalar381::zero();
+        let mut power = self.input;
+        let ciphertexts = choices.iter().enumerate().map(|(i, choice)| {
+            let mut s = random_scalar381(rng);
+            let pad_0 = pad(i, &self.key, choice, &(self.secret * choice));
+            let pad_1 = pad(i, &self.key, choice, &(self.secret * (choice - self.key)));
+            let ciphertext = [s + pad_0, s + power + pad_1];
+            share -= s;
+            power = power.double();
+            s.zeroize();
+            ciphertext
+        }).collect();
+        (share, ciphertexts)
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        self.input.zeroize();
+        self.secret.zeroize();
+    }
+}
+
+pub(crate) struct Receiver {
+    bits: Vec<u8>,
+    secrets: Vec<Scalar25519>,
+    choices: Vec<RistrettoPoint>,
+    sender_key: RistrettoPoint,
+}
+
+impl Receiver {
+    /// Chooses, for every bit `b_i` of `input`, the key `x_i G + b_i A`.
+    pub(crate) fn new<R: RngCore + CryptoRng>(input: &Scalar381, sender_key: RistrettoPoint, rng: &mut R) -> Self {
+        let mut bytes = input.to_bytes();
+        let bits: Vec<u8> = (0..OT_COUNT).map(|i| (bytes[i / 8] >> (i % 8)) & 1).collect();
+        bytes.zeroize();
+        let secrets: Vec<Scalar25519> = (0..OT_COUNT).map(|_| Scalar25519::random(rng)).collect();
+        let choices = bits.iter().zip(&secrets).map(|(bit, secret)| {
+            let point = secret * &RISTRETTO_BASEPOINT_TABLE;
+            if *bit == 1 { point + sender_key } else { point }
+        }).collect();
+        Receiver { bits, secrets, choices, sender_key }
+    }
+
+    pub(crate) fn choices(&self) -> &[RistrettoPoint] {
+        &self.choices
+    }
+
+    /// Decrypts the chosen messages and returns the receiver's share, their sum.
+    pub(crate) fn finish(&self, ciphertexts: &[[Scalar381; 2]]) -> Scalar381 {
+        assert_eq!(ciphertexts.len(), OT_COUNT);
+        ciphertexts.iter().enumerate().fold(Scalar381::zero(), |share, (i, ciphertext)| {
+            let pad = pad(i, &self.sender_key, &self.choices[i], &(self.secrets[i] * self.sender_key));
+            share + ciphertext[self.bits[i] as usize] - pad
+        })
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        self.bits.zeroize();
+        self.secrets.zeroize();
+    }
+}
+
+fn pad(i: usize, sender_key: &RistrettoPoint, choice: &RistrettoPoint, shared: &RistrettoPoint) -> Scalar381 {
+    let mut hash = Sha512::default();
+    hash.update(MTA_DOMAIN);
+    hash.update((i as u64).to_le_bytes());
+    hash.update(sender_key.compress().as_bytes());
+    hash.update(choice.compress().as_bytes());
+    hash.update(shared.compress().as_bytes());
+    Scalar381::from_bytes_wide(hash.finalize().as_slice().try_into().unwrap())
+}
+
+pub(crate) fn random_scalar381<R: RngCore + CryptoRng>(rng: &mut R) -> Scalar381 {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+    let scalar = Scalar381::from_bytes_wide(&bytes);
+    bytes.zeroize();
+    scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_mta() {
+        let mut rng = thread_rng();
+        let a = random_scalar381(&mut rng);
+        let b = random_scalar381(&mut rng);
+        let sender = Sender::new(a, &mut rng);
+        let receiver = Receiver::new(&b, sender.key(), &mut rng);
+        let (alpha, ciphertexts) = sender.respond(receiver.choices(), &mut rng);
+        let beta = receiver.finish(&ciphertexts);
+        assert_eq!(alpha + beta, a * b);
+        assert_ne!(alpha, Scalar381::zero());
+    }
+}
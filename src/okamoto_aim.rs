@@ -1,71 +1,276 @@
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
-use sha2::{Digest, Sha512};
+use rand::{CryptoRng, RngCore, thread_rng};
 
-use super::{aggregate_curve25519, sample_curve25519_lambda, hash_tilde_curve25519, curve25519_scalar_list_to_bytes};
+use super::batch;
+use super::encoding::{check_length, scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar25519_from_bytes, ristretto_from_bytes};
+use super::secret::SecretVec;
+use super::generic::{self, Ristretto, RistrettoScalar, from_ristretto_scalars, to_ristretto_scalars};
+use super::{ByteEncoding, SecretEncoding, Error, Params, OKAMOTO_BASE_POINT2 as BASE_POINT2, SignatureScheme, HashTildeVersion, assert_nonempty, validate_canonical, validate_ristretto_public_key};
 
-lazy_static::lazy_static! {
-    static ref BASE_POINT2: RistrettoPoint = {
-        let mut rng = rand::rngs::ThreadRng::default();
-        RistrettoPoint::random(&mut rng)
-    };
+pub fn keygen(n: usize) -> (Vec<Scalar>, RistrettoPoint) {
+    keygen_with_rng(n, &mut thread_rng())
 }
 
-pub fn keygen(n: usize) -> (Vec<Scalar>, RistrettoPoint) {
-    let sk = sample_curve25519_lambda(n);
-    let mut sk_hash = curve25519_scalar_list_to_bytes(&sk);
-    sk_hash.push(0);
-    let a_list = hash_tilde_curve25519(&sk_hash, n, false);
-    *sk_hash.last_mut().unwrap() = 1;
-    let b_list = hash_tilde_curve25519(&sk_hash, n, false);
-    let pk = RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&a_list, &sk) + *BASE_POINT2 * aggregate_curve25519(&b_list, &sk);
-    (sk, pk)
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    keygen_inner(&Params::new(n), rng)
 }
 
 pub fn sign(pk: &RistrettoPoint, sk: &[Scalar], msg: &[u8]) -> (Scalar, Scalar, Scalar) {
-    let n = sk.len();
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
+    sign_with_rng(pk, sk, msg, &mut thread_rng())
+}
 
-    let mut sk_hash = curve25519_scalar_list_to_bytes(&sk);
-    sk_hash.push(0);
-    let a_list = hash_tilde_curve25519(&sk_hash, n, false);
-    *sk_hash.last_mut().unwrap() = 1;
-    let b_list = hash_tilde_curve25519(&sk_hash, n, false);
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pk: &RistrettoPoint, sk: &[Scalar], msg: &[u8], rng: &mut R) -> (Scalar, Scalar, Scalar) {
+    let (_, c, z_1, z_2) = sign_inner(&expand_inner(HashTildeVersion::default(), sk), pk, msg, rng);
+    (c, z_1, z_2)
+}
 
-    let r_list = sample_curve25519_lambda(n);
-    let mut r_hash = curve25519_scalar_list_to_bytes(&r_list);
-    r_hash.push(0);
-    let d_list = hash_tilde_curve25519(&r_hash, n, false);
-    *r_hash.last_mut().unwrap() = 1;
-    let e_list = hash_tilde_curve25519(&r_hash, n, false);
+/// Signs in the `R || z_1 || z_2` form accepted by `verify_batch`.
+pub fn sign_batchable(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8]) -> BatchSignature {
+    sign_batchable_with_rng(esk, pk, msg, &mut thread_rng())
+}
 
-    let r_point = RISTRETTO_BASEPOINT_POINT *aggregate_curve25519(&d_list, &r_list) + *BASE_POINT2 * aggregate_curve25519(&e_list, &r_list);
+pub fn sign_batchable_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> BatchSignature {
+    let (r, _, z_1, z_2) = sign_inner(esk, &pk.0, msg, rng);
+    BatchSignature { r, z_1, z_2 }
+}
 
-    let mut c_hash = Sha512::default();
-    c_hash.update(pk.compress().as_bytes());
-    c_hash.update(msg_hash.as_bytes());
-    c_hash.update(r_point.compress().as_bytes());
-    let c = Scalar::from_hash::<Sha512>(c_hash);
+pub fn verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar, Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
 
-    let z_1 = aggregate_curve25519(&d_list, &r_list) + c * aggregate_curve25519(&a_list, &sk);
-    let z_2 = aggregate_curve25519(&e_list, &r_list) + c * aggregate_curve25519(&b_list, &sk);
+pub fn try_verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar, Scalar)) -> Result<(), Error> {
+    let (c, z_1, z_2) = signature;
+    validate_ristretto_public_key(pk)?;
+    validate_canonical(&[c, z_1, z_2])?;
+    generic::okamoto::try_verify(&Ristretto(*pk), msg, &(RistrettoScalar(*c), RistrettoScalar(*z_1), RistrettoScalar(*z_2)))
+}
 
-    (c, z_1, z_2)
+/// Verifies every `(pk, msg, signature)` entry, returning the indices of the
+/// invalid ones.
+pub fn verify_batch(entries: &[(VerifyingKey, &[u8], BatchSignature)]) -> Result<(), Vec<usize>> {
+    verify_batch_with_rng(entries, &mut thread_rng())
 }
 
-pub fn verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar, Scalar)) -> bool {
-    let (c, z_1, z_2) = signature;
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
+pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(entries: &[(VerifyingKey, &[u8], BatchSignature)], rng: &mut R) -> Result<(), Vec<usize>> {
+    let equations: Vec<batch::Equation> = entries.iter().map(|(pk, msg, signature)| batch::Equation {
+        pk: pk.0,
+        r: signature.r,
+        c: challenge(&pk.0, msg, &signature.r),
+        z: vec![signature.z_1, signature.z_2],
+        valid: signature.validate(pk).is_ok(),
+    }).collect();
+    batch::verify_with_rng(&[RISTRETTO_BASEPOINT_POINT, *BASE_POINT2], &equations, rng)
+}
+
+fn challenge(pk: &RistrettoPoint, msg: &[u8], point: &RistrettoPoint) -> Scalar {
+    generic::okamoto::challenge(&Ristretto(*pk), msg, &Ristretto(*point)).0
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    let (sk, pk) = generic::okamoto::keygen_inner::<Ristretto, R>(params, rng);
+    (from_ristretto_scalars(sk), pk.0)
+}
+
+fn expand_inner(version: HashTildeVersion, sk: &[Scalar]) -> ExpandedSigningKey {
+    generic::okamoto::expand_inner(version, to_ristretto_scalars(sk))
+}
 
-    let mut c_candidate_hash = Sha512::default();
-    c_candidate_hash.update(pk.compress().as_bytes());
-    c_candidate_hash.update(msg_hash.as_bytes());
-    let r_point = RISTRETTO_BASEPOINT_POINT * z_1 + *BASE_POINT2 * z_2;
-    let r_point = r_point - pk * c;
-    c_candidate_hash.update(r_point.compress().as_bytes());
+fn public_key_inner(version: HashTildeVersion, sk: &[Scalar]) -> RistrettoPoint {
+    generic::okamoto::public_key_inner::<Ristretto>(version, to_ristretto_scalars(sk)).0
+}
 
-    c == &Scalar::from_hash::<Sha512>(c_candidate_hash)
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &RistrettoPoint, msg: &[u8], rng: &mut R) -> (RistrettoPoint, Scalar, Scalar, Scalar) {
+    let (point, c, z_1, z_2) = generic::okamoto::sign_inner(esk, &Ristretto(*pk), msg, rng);
+    (point.0, c.0, z_1.0, z_2.0)
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey(pub(crate) SecretVec<Scalar>);
+
+impl SigningKey {
+    pub fn as_scalars(&self) -> &[Scalar] {
+        &self.0
+    }
+}
+
+impl From<Vec<Scalar>> for SigningKey {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, scalar25519_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secrets `<a, sk>` and `<b, sk>` of `generic::okamoto`.
+pub type ExpandedSigningKey = generic::okamoto::ExpandedSigningKey<Ristretto>;
+
+/// Compressed Ristretto point, 32 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(pub RistrettoPoint);
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 32;
+
+    /// Rejects the identity. Every Ristretto point lies in the prime-order
+    /// group, so no subgroup check is needed.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_ristretto_public_key(&self.0)
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.compress().to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(ristretto_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `c || z_1 || z_2`, three canonical scalars, 96 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub c: Scalar,
+    pub z_1: Scalar,
+    pub z_2: Scalar,
+}
+
+impl Signature {
+    pub const BYTE_LEN: usize = 96;
+}
+
+impl From<(Scalar, Scalar, Scalar)> for Signature {
+    fn from((c, z_1, z_2): (Scalar, Scalar, Scalar)) -> Self {
+        Signature { c, z_1, z_2 }
+    }
+}
+
+impl From<Signature> for (Scalar, Scalar, Scalar) {
+    fn from(signature: Signature) -> Self {
+        (signature.c, signature.z_1, signature.z_2)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.c.as_bytes());
+        bytes.extend_from_slice(self.z_1.as_bytes());
+        bytes.extend_from_slice(self.z_2.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Signature { c: scalar25519_from_bytes(&bytes[0..32])?, z_1: scalar25519_from_bytes(&bytes[32..64])?, z_2: scalar25519_from_bytes(&bytes[64..96])? })
+    }
+}
+
+/// `R || z_1 || z_2`, the compressed commitment and the responses, 96 bytes.
+/// Unlike `Signature` it can be checked by `verify_batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchSignature {
+    pub r: RistrettoPoint,
+    pub z_1: Scalar,
+    pub z_2: Scalar,
+}
+
+impl BatchSignature {
+    pub const BYTE_LEN: usize = 96;
+
+    /// Recovers the commitment of a `c || z_1 || z_2` signature, at the cost of a verification.
+    pub fn from_signature(pk: &VerifyingKey, signature: &Signature) -> Self {
+        let Signature { c, z_1, z_2 } = *signature;
+        BatchSignature { r: RISTRETTO_BASEPOINT_POINT * z_1 + *BASE_POINT2 * z_2 - pk.0 * c, z_1, z_2 }
+    }
+
+    pub fn to_signature(&self, pk: &VerifyingKey, msg: &[u8]) -> Signature {
+        Signature { c: challenge(&pk.0, msg, &self.r), z_1: self.z_1, z_2: self.z_2 }
+    }
+
+    fn validate(&self, pk: &VerifyingKey) -> Result<(), Error> {
+        pk.validate()?;
+        validate_canonical(&[&self.z_1, &self.z_2])
+    }
+}
+
+impl ByteEncoding for BatchSignature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.r.compress().as_bytes());
+        bytes.extend_from_slice(self.z_1.as_bytes());
+        bytes.extend_from_slice(self.z_2.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(BatchSignature { r: ristretto_from_bytes(&bytes[0..32])?, z_1: scalar25519_from_bytes(&bytes[32..64])?, z_2: scalar25519_from_bytes(&bytes[64..96])? })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+impl_serde_via_bytes!(BatchSignature);
+
+pub struct OkamotoAim;
+
+impl SignatureScheme for OkamotoAim {
+    const NAME: &'static str = "LR-Okamoto-AIM";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        let (_, c, z_1, z_2) = sign_inner(esk, &pk.0, msg, rng);
+        Ok(Signature { c, z_1, z_2 })
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
 }
 
 #[cfg(test)]
@@ -76,7 +281,58 @@ mod tests {
         let n = 100;
         let msg = "Hello, world!";
         let (sk, pk) = keygen(n);
-        let signature = sign(&pk, &sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let signature = sign(&pk, &sk, msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lr_okamoto_aim_verify_batch() {
+        let params = Params::new(20);
+        let messages: Vec<Vec<u8>> = (0..8).map(|i| format!("message {}", i).into_bytes()).collect();
+        let mut entries: Vec<(VerifyingKey, &[u8], BatchSignature)> = messages.iter().map(|msg| {
+            let (sk, pk) = OkamotoAim::keygen(&params);
+            let signature = sign_batchable(&OkamotoAim::expand(&params, &sk), &pk, msg);
+            assert!(OkamotoAim::verify(&params, &pk, msg, &signature.to_signature(&pk, msg)));
+            (pk, msg.as_slice(), signature)
+        }).collect();
+        assert_eq!(verify_batch(&entries), Ok(()));
+
+        entries[0].2.z_2 += Scalar::one();
+        entries[7].1 = b"forged";
+        assert_eq!(verify_batch(&entries), Err(vec![0, 7]));
+    }
+
+    #[test]
+    fn test_lr_okamoto_aim_batch_signature_conversion() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = OkamotoAim::keygen(&params);
+        let signature = OkamotoAim::sign(&params, &sk, &pk, msg);
+        let batch_signature = BatchSignature::from_signature(&pk, &signature);
+        assert_eq!(batch_signature.to_signature(&pk, msg), signature);
+        assert_eq!(BatchSignature::from_bytes(&batch_signature.to_bytes()), Ok(batch_signature));
+    }
+
+    #[test]
+    fn test_lr_okamoto_aim_deterministic() {
+        let params = Params::new(20);
+        let sk: Vec<Scalar> = (1..=20u64).map(Scalar::from).collect();
+        let sk = SigningKey::from(sk);
+        let pk = OkamotoAim::public_key(&params, &sk);
+        let msg = b"Hello, world!";
+        let signature = OkamotoAim::sign_deterministic(&params, &sk, &pk, msg);
+        assert!(OkamotoAim::verify(&params, &pk, msg, &signature));
+        assert_eq!(OkamotoAim::sign_deterministic(&params, &sk, &pk, msg), signature);
+        let hex: String = signature.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "3806b865a0f40bf36c3e667e7731c1d536618ed983ad299d11b9da5fb6ce1f0d4d547d44c700de9683337cff4fdd72f1b75a27e10485a054cbac5ba55c208b05190eba0f0b70b266a365532735cc699968170ae550b00ba783074d0c4cc1b008");
+
+        let hedged = OkamotoAim::sign_hedged(&params, &sk, &pk, msg);
+        assert!(OkamotoAim::verify(&params, &pk, msg, &hedged));
+        assert_ne!(hedged, signature);
+    }
+
+    #[test]
+    fn test_lr_okamoto_aim_rejects_identity_key() {
+        assert_eq!(VerifyingKey::from_bytes(&[0u8; 32]), Err(Error::IdentityPublicKey));
+    }
+}
@@ -2,66 +2,369 @@ use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
 use sha2::{Digest, Sha512};
+use rand::{CryptoRng, RngCore, thread_rng};
+use zeroize::{Zeroize, Zeroizing};
 
-use super::{aggregate_curve25519, sample_curve25519_lambda, hash_tilde_curve25519, curve25519_scalar_list_to_bytes};
+use super::batch;
+use super::encoding::{check_length, shares_from_bytes, shares_to_bytes, scalar25519_from_bytes, ristretto_from_bytes};
+use super::secret::SecretVec;
+use super::{ByteEncoding, SecretEncoding, Error, Params, OKAMOTO_BASE_POINT2 as BASE_POINT2, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_curve25519, refresh_share_curve25519, sample_curve25519_lambda_with_rng, hash_tilde_curve25519, curve25519_scalar_list_to_bytes, validate_canonical, validate_ristretto_public_key};
 
-lazy_static::lazy_static! {
-    static ref BASE_POINT2: RistrettoPoint = {
-        let mut rng = rand::rngs::ThreadRng::default();
-        RistrettoPoint::random(&mut rng)
-    };
-}
+pub mod split;
 
 pub fn keygen(n: usize) -> ((Vec<Scalar>, Vec<Scalar>), RistrettoPoint) {
-    let sk1 = sample_curve25519_lambda(n);
-    let sk2 = sample_curve25519_lambda(n);
-    let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk1), n, false);
-    let b_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk2), n, false);
-    let pk = RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&a_list, &sk1) + *BASE_POINT2 * aggregate_curve25519(&b_list, &sk2);
-    ((sk1, sk2), pk)
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> ((Vec<Scalar>, Vec<Scalar>), RistrettoPoint) {
+    let (sk, pk) = keygen_inner(&Params::new(n), rng);
+    ((sk.sk1.into_vec(), sk.sk2.into_vec()), pk)
+}
+
+/// Signs in the `R || z_1 || z_2` form accepted by `verify_batch`.
+pub fn sign_batchable(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8]) -> BatchSignature {
+    sign_batchable_with_rng(esk, pk, msg, &mut thread_rng())
+}
+
+pub fn sign_batchable_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> BatchSignature {
+    let (r, _, z_1, z_2) = sign_inner(esk, &pk.0, msg, rng);
+    BatchSignature { r, z_1, z_2 }
+}
+
+/// Re-randomizes both shares without changing the public key.
+///
+/// Each share moves by a random vector orthogonal to its coefficient vector,
+/// so the aggregated secrets stay the same while the shares themselves change.
+/// The coefficients of a fresh key are derived under `params.hash_version`.
+/// Panics if `n < 2`.
+pub fn refresh<R: RngCore + CryptoRng>(params: &Params, sk: &SigningKey, rng: &mut R) -> SigningKey {
+    let (a_list, b_list) = sk.coefficients(params.hash_version);
+    SigningKey {
+        sk1: refresh_share_curve25519(&sk.sk1, &a_list, rng).into(),
+        sk2: refresh_share_curve25519(&sk.sk2, &b_list, rng).into(),
+        coefficients: Some((SecretVec::from_slice(&a_list), SecretVec::from_slice(&b_list))),
+    }
 }
 
-pub fn sign(pk: &RistrettoPoint, sk: &(Vec<Scalar>, Vec<Scalar>), msg: &[u8]) -> (Scalar, Scalar, Scalar) {
-    let (sk1, sk2) = sk;
-    let n = sk1.len();
+pub fn verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar, Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar, Scalar)) -> Result<(), Error> {
+    let (c, z_1, z_2) = signature;
+    validate_ristretto_public_key(pk)?;
+    validate_canonical(&[c, z_1, z_2])?;
+    let r_point = RISTRETTO_BASEPOINT_POINT * z_1 + *BASE_POINT2 * z_2;
+    let r_point = r_point - pk * c;
+
+    if c == &challenge(pk, msg, &r_point) {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+/// Verifies every `(pk, msg, signature)` entry, returning the indices of the
+/// invalid ones.
+pub fn verify_batch(entries: &[(VerifyingKey, &[u8], BatchSignature)]) -> Result<(), Vec<usize>> {
+    verify_batch_with_rng(entries, &mut thread_rng())
+}
+
+pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(entries: &[(VerifyingKey, &[u8], BatchSignature)], rng: &mut R) -> Result<(), Vec<usize>> {
+    let equations: Vec<batch::Equation> = entries.iter().map(|(pk, msg, signature)| batch::Equation {
+        pk: pk.0,
+        r: signature.r,
+        c: challenge(&pk.0, msg, &signature.r),
+        z: vec![signature.z_1, signature.z_2],
+        valid: signature.validate(pk).is_ok(),
+    }).collect();
+    batch::verify_with_rng(&[RISTRETTO_BASEPOINT_POINT, *BASE_POINT2], &equations, rng)
+}
+
+fn challenge(pk: &RistrettoPoint, msg: &[u8], point: &RistrettoPoint) -> Scalar {
     let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
+    let mut c_hash = Sha512::default();
+    c_hash.update(pk.compress().as_bytes());
+    c_hash.update(msg_hash.as_bytes());
+    c_hash.update(point.compress().as_bytes());
+    Scalar::from_hash::<Sha512>(c_hash)
+}
 
-    let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk1), n, false);
-    let b_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk2), n, false);
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, RistrettoPoint) {
+    assert_nonempty(params.n);
+    let sk1 = sample_curve25519_lambda_with_rng(params.n, rng);
+    let sk2 = sample_curve25519_lambda_with_rng(params.n, rng);
+    let sk = SigningKey::from_shares(sk1, sk2);
+    let pk = sk.public_key(params.hash_version);
+    (sk, pk)
+}
+
+// The coefficients of a share straight from `keygen`, hashed from the share.
+fn coefficients(share: &[Scalar], version: HashTildeVersion) -> Zeroizing<Vec<Scalar>> {
+    Zeroizing::new(hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(share), share.len(), false, version))
+}
+
+fn expand_inner(version: HashTildeVersion, sk1: &[Scalar], a_list: &[Scalar], sk2: &[Scalar], b_list: &[Scalar]) -> ExpandedSigningKey {
+    assert_nonempty(sk1.len());
+    ExpandedSigningKey { n: sk1.len(), version, x_1: aggregate_curve25519(a_list, sk1), x_2: aggregate_curve25519(b_list, sk2) }
+}
 
-    let r_list = sample_curve25519_lambda(n);
+fn public_key_inner(sk1: &[Scalar], a_list: &[Scalar], sk2: &[Scalar], b_list: &[Scalar]) -> RistrettoPoint {
+    RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(a_list, sk1) + *BASE_POINT2 * aggregate_curve25519(b_list, sk2)
+}
+
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &RistrettoPoint, msg: &[u8], rng: &mut R) -> (RistrettoPoint, Scalar, Scalar, Scalar) {
+    let (n, version) = (esk.n, esk.version);
+
+    let r_list = Zeroizing::new(sample_curve25519_lambda_with_rng(n, rng));
     let mut r_hash = curve25519_scalar_list_to_bytes(&r_list);
     r_hash.push(0);
-    let d_list = hash_tilde_curve25519(&r_hash, n, false);
+    let d_list = Zeroizing::new(hash_tilde_curve25519(&r_hash, n, false, version));
     *r_hash.last_mut().unwrap() = 1;
-    let e_list = hash_tilde_curve25519(&r_hash, n, false);
+    let e_list = Zeroizing::new(hash_tilde_curve25519(&r_hash, n, false, version));
 
     let r_point = RISTRETTO_BASEPOINT_POINT *aggregate_curve25519(&d_list, &r_list) + *BASE_POINT2 * aggregate_curve25519(&e_list, &r_list);
 
-    let mut c_hash = Sha512::default();
-    c_hash.update(pk.compress().as_bytes());
-    c_hash.update(msg_hash.as_bytes());
-    c_hash.update(r_point.compress().as_bytes());
-    let c = Scalar::from_hash::<Sha512>(c_hash);
+    let c = challenge(pk, msg, &r_point);
 
-    let z_1 = aggregate_curve25519(&d_list, &r_list) + c * aggregate_curve25519(&a_list, &sk1);
-    let z_2 = aggregate_curve25519(&e_list, &r_list) + c * aggregate_curve25519(&b_list, &sk2);
+    let z_1 = aggregate_curve25519(&d_list, &r_list) + c * esk.x_1;
+    let z_2 = aggregate_curve25519(&e_list, &r_list) + c * esk.x_2;
 
-    (c, z_1, z_2)
+    (r_point, c, z_1, z_2)
 }
 
-pub fn verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar, Scalar)) -> bool {
-    let (c, z_1, z_2) = signature;
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
+/// The two shares, and once refreshed the coefficient vectors they are
+/// aggregated with.
+///
+/// The coefficients of a fresh key are hashed from its shares under
+/// `Params::hash_version`. A refreshed share no longer determines them, so
+/// `refresh` stores them in the key. Encoded as `n` (u64 LE) followed by
+/// `sk1` and `sk2`, or by `sk1`, `a_list`, `sk2` and `b_list` once refreshed,
+/// `n` 32-byte scalars each.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    pub(crate) sk1: SecretVec<Scalar>,
+    pub(crate) sk2: SecretVec<Scalar>,
+    pub(crate) coefficients: Option<(SecretVec<Scalar>, SecretVec<Scalar>)>,
+}
 
-    let mut c_candidate_hash = Sha512::default();
-    c_candidate_hash.update(pk.compress().as_bytes());
-    c_candidate_hash.update(msg_hash.as_bytes());
-    let r_point = RISTRETTO_BASEPOINT_POINT * z_1 + *BASE_POINT2 * z_2;
-    let r_point = r_point - pk * c;
-    c_candidate_hash.update(r_point.compress().as_bytes());
+impl SigningKey {
+    /// Wraps shares produced by `keygen`. Panics if they differ in length or
+    /// are empty.
+    pub fn from_shares(sk1: Vec<Scalar>, sk2: Vec<Scalar>) -> Self {
+        assert_eq!(sk1.len(), sk2.len());
+        assert_nonempty(sk1.len());
+        SigningKey { sk1: sk1.into(), sk2: sk2.into(), coefficients: None }
+    }
 
-    c == &Scalar::from_hash::<Sha512>(c_candidate_hash)
+    pub(crate) fn coefficients(&self, version: HashTildeVersion) -> (Zeroizing<Vec<Scalar>>, Zeroizing<Vec<Scalar>>) {
+        match &self.coefficients {
+            Some((a_list, b_list)) => (Zeroizing::new(a_list.to_vec()), Zeroizing::new(b_list.to_vec())),
+            None => (coefficients(&self.sk1, version), coefficients(&self.sk2, version)),
+        }
+    }
+
+    fn public_key(&self, version: HashTildeVersion) -> RistrettoPoint {
+        let (a_list, b_list) = self.coefficients(version);
+        public_key_inner(&self.sk1, &a_list, &self.sk2, &b_list)
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.sk1.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        let coefficients = self.coefficients.as_ref().map(|(a_list, b_list)| [&a_list[..], &b_list[..]]);
+        shares_to_bytes(&[&self.sk1[..], &self.sk2[..]], coefficients.as_ref().map(|c| &c[..]), Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (mut shares, coefficients) = shares_from_bytes(bytes, 2, scalar25519_from_bytes)?;
+        let coefficients = coefficients.map(|mut c| {
+            let b_list = c.pop().unwrap();
+            (c.pop().unwrap().into(), b_list.into())
+        });
+        let sk2 = shares.pop().unwrap().into();
+        Ok(SigningKey { sk1: shares.pop().unwrap().into(), sk2, coefficients })
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secrets `<a, sk1>` and `<b, sk2>`, with the length and hash
+/// version used to derive nonces.
+#[derive(Clone)]
+pub struct ExpandedSigningKey {
+    n: usize,
+    version: HashTildeVersion,
+    x_1: Scalar,
+    x_2: Scalar,
+}
+
+impl std::fmt::Debug for ExpandedSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ExpandedSigningKey(n = {})", self.n)
+    }
+}
+
+impl Drop for ExpandedSigningKey {
+    fn drop(&mut self) {
+        self.x_1.zeroize();
+        self.x_2.zeroize();
+    }
+}
+
+/// Compressed Ristretto point, 32 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(pub RistrettoPoint);
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 32;
+
+    /// Rejects the identity. Every Ristretto point lies in the prime-order
+    /// group, so no subgroup check is needed.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_ristretto_public_key(&self.0)
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.compress().to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(ristretto_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `c || z_1 || z_2`, three canonical scalars, 96 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub c: Scalar,
+    pub z_1: Scalar,
+    pub z_2: Scalar,
+}
+
+impl Signature {
+    pub const BYTE_LEN: usize = 96;
+}
+
+impl From<(Scalar, Scalar, Scalar)> for Signature {
+    fn from((c, z_1, z_2): (Scalar, Scalar, Scalar)) -> Self {
+        Signature { c, z_1, z_2 }
+    }
+}
+
+impl From<Signature> for (Scalar, Scalar, Scalar) {
+    fn from(signature: Signature) -> Self {
+        (signature.c, signature.z_1, signature.z_2)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.c.as_bytes());
+        bytes.extend_from_slice(self.z_1.as_bytes());
+        bytes.extend_from_slice(self.z_2.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Signature { c: scalar25519_from_bytes(&bytes[0..32])?, z_1: scalar25519_from_bytes(&bytes[32..64])?, z_2: scalar25519_from_bytes(&bytes[64..96])? })
+    }
+}
+
+/// `R || z_1 || z_2`, the compressed commitment and the responses, 96 bytes.
+/// Unlike `Signature` it can be checked by `verify_batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchSignature {
+    pub r: RistrettoPoint,
+    pub z_1: Scalar,
+    pub z_2: Scalar,
+}
+
+impl BatchSignature {
+    pub const BYTE_LEN: usize = 96;
+
+    /// Recovers the commitment of a `c || z_1 || z_2` signature, at the cost of a verification.
+    pub fn from_signature(pk: &VerifyingKey, signature: &Signature) -> Self {
+        let Signature { c, z_1, z_2 } = *signature;
+        BatchSignature { r: RISTRETTO_BASEPOINT_POINT * z_1 + *BASE_POINT2 * z_2 - pk.0 * c, z_1, z_2 }
+    }
+
+    pub fn to_signature(&self, pk: &VerifyingKey, msg: &[u8]) -> Signature {
+        Signature { c: challenge(&pk.0, msg, &self.r), z_1: self.z_1, z_2: self.z_2 }
+    }
+
+
+    fn validate(&self, pk: &VerifyingKey) -> Result<(), Error> {
+        pk.validate()?;
+        validate_canonical(&[&self.z_1, &self.z_2])
+    }
+}
+
+impl ByteEncoding for BatchSignature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.r.compress().as_bytes());
+        bytes.extend_from_slice(self.z_1.as_bytes());
+        bytes.extend_from_slice(self.z_2.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(BatchSignature { r: ristretto_from_bytes(&bytes[0..32])?, z_1: scalar25519_from_bytes(&bytes[32..64])?, z_2: scalar25519_from_bytes(&bytes[64..96])? })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+impl_serde_via_bytes!(BatchSignature);
+
+pub struct OkamotoCml;
+
+impl SignatureScheme for OkamotoCml {
+    const NAME: &'static str = "LR-Okamoto-CML";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (sk, VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        VerifyingKey(sk.public_key(params.hash_version))
+    }
+
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        let (a_list, b_list) = sk.coefficients(params.hash_version);
+        expand_inner(params.hash_version, &sk.sk1, &a_list, &sk.sk2, &b_list)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        let (_, c, z_1, z_2) = sign_inner(esk, &pk.0, msg, rng);
+        Ok(Signature { c, z_1, z_2 })
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
 }
 
 #[cfg(test)]
@@ -71,8 +374,106 @@ mod tests {
     fn test_lr_okamoto_cml() {
         let n = 100;
         let msg = "Hello, world!";
-        let (sk, pk) = keygen(n);
-        let signature = sign(&pk, &sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let ((sk1, sk2), pk) = keygen(n);
+        let signature = OkamotoCml::sign(&Params::new(n), &SigningKey::from_shares(sk1, sk2), &VerifyingKey(pk), msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature.into()))
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_refresh() {
+        let params = Params::new(20);
+        let msg = "Hello, world!";
+        let mut rng = thread_rng();
+        let (mut sk, pk) = OkamotoCml::keygen(&params);
+        for _ in 0..3 {
+            let refreshed = refresh(&params, &sk, &mut rng);
+            assert!(refreshed.sk1.iter().zip(sk.sk1.iter()).all(|(a, b)| a != b));
+            assert!(refreshed.sk2.iter().zip(sk.sk2.iter()).all(|(a, b)| a != b));
+            assert_eq!(OkamotoCml::public_key(&params, &refreshed), pk);
+            let signature = OkamotoCml::sign(&params, &refreshed, &pk, msg.as_bytes());
+            assert!(OkamotoCml::verify(&params, &pk, msg.as_bytes(), &signature));
+            let decoded = SigningKey::from_bytes(&refreshed.to_bytes()).unwrap();
+            assert_eq!(OkamotoCml::public_key(&params, &decoded), pk);
+            sk = refreshed;
+        }
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_verify_batch() {
+        let params = Params::new(20);
+        let messages: Vec<Vec<u8>> = (0..8).map(|i| format!("message {}", i).into_bytes()).collect();
+        let mut entries: Vec<(VerifyingKey, &[u8], BatchSignature)> = messages.iter().map(|msg| {
+            let (sk, pk) = OkamotoCml::keygen(&params);
+            let signature = sign_batchable(&OkamotoCml::expand(&params, &sk), &pk, msg);
+            assert!(OkamotoCml::verify(&params, &pk, msg, &signature.to_signature(&pk, msg)));
+            (pk, msg.as_slice(), signature)
+        }).collect();
+        assert_eq!(verify_batch(&entries), Ok(()));
+
+        entries[0].2.z_2 += Scalar::one();
+        entries[7].1 = b"forged";
+        assert_eq!(verify_batch(&entries), Err(vec![0, 7]));
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_batch_signature_conversion() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = OkamotoCml::keygen(&params);
+        let signature = OkamotoCml::sign(&params, &sk, &pk, msg);
+        let batch_signature = BatchSignature::from_signature(&pk, &signature);
+        assert_eq!(batch_signature.to_signature(&pk, msg), signature);
+        assert_eq!(BatchSignature::from_bytes(&batch_signature.to_bytes()), Ok(batch_signature));
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_legacy_coefficients() {
+        let params = Params { n: 20, hash_version: HashTildeVersion::V0 };
+        let ((sk1, sk2), _) = keygen(params.n);
+        // As derived before keys could be refreshed.
+        let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk1), params.n, false, params.hash_version);
+        let b_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk2), params.n, false, params.hash_version);
+        let pk = VerifyingKey(RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&a_list, &sk1) + *BASE_POINT2 * aggregate_curve25519(&b_list, &sk2));
+        let sk = SigningKey::from_shares(sk1, sk2);
+        assert_eq!(crate::detect_hash_version::<OkamotoCml>(params.n, &sk, &pk), Some(HashTildeVersion::V0));
+        let refreshed = refresh(&params, &sk, &mut thread_rng());
+        assert_eq!(OkamotoCml::public_key(&params, &refreshed), pk);
+        let msg = b"Hello, world!";
+        assert!(OkamotoCml::verify(&params, &pk, msg, &OkamotoCml::sign(&params, &refreshed, &pk, msg)));
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_deterministic() {
+        let params = Params::new(20);
+        let sk1: Vec<Scalar> = (1..=20u64).map(Scalar::from).collect();
+        let sk2: Vec<Scalar> = (21..=40u64).map(Scalar::from).collect();
+        let sk = SigningKey::from_shares(sk1, sk2);
+        let pk = OkamotoCml::public_key(&params, &sk);
+        let msg = b"Hello, world!";
+        let signature = OkamotoCml::sign_deterministic(&params, &sk, &pk, msg);
+        assert!(OkamotoCml::verify(&params, &pk, msg, &signature));
+        assert_eq!(OkamotoCml::sign_deterministic(&params, &sk, &pk, msg), signature);
+        let hex: String = signature.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "948f265f6488c9e557f6c1c6fc474ae9d833ae311a3b3d5846eba06551552f093c33beb838d20f8fb4a608972ae19609294a7e979375e326014c86843eca5601e545fd724d3a29af50035666eee01714dfad905145bb4c00f7978a62dcc18803");
+
+        let hedged = OkamotoCml::sign_hedged(&params, &sk, &pk, msg);
+        assert!(OkamotoCml::verify(&params, &pk, msg, &hedged));
+        assert_ne!(hedged, signature);
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_rejects_identity_key() {
+        assert_eq!(VerifyingKey::from_bytes(&[0u8; 32]), Err(Error::IdentityPublicKey));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lr_okamoto_cml_refreshed_key_signs_deterministically() {
+        let params = Params::new(20);
+        let msg = "Hello, world!".as_bytes();
+        let (sk, pk) = OkamotoCml::keygen(&params);
+        let refreshed = refresh(&params, &sk, &mut thread_rng());
+        let signature = OkamotoCml::sign_deterministic(&params, &refreshed, &pk, msg);
+        assert!(OkamotoCml::verify(&params, &pk, msg, &signature));
+        assert_eq!(OkamotoCml::sign_deterministic(&params, &refreshed, &pk, msg), signature);
+    }
+}
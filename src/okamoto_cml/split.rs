@@ -0,0 +1,262 @@
+//! Two-party signing with the halves of a `SigningKey` on separate devices.
+//!
+//! Party 1 holds `sk1` and party 2 holds `sk2`. Each derives its own nonce
+//! from a fresh nonce vector and only ever touches its own share:
+//!
+//! 1. Party 1 sends `Round1`, its commitment `R_1 = k_1 G`.
+//! 2. Party 2 answers with `Round2`, its commitment `R_2 = k_2 H` and its
+//!    response `z_2 = k_2 + c x_2` to the challenge on `R_1 + R_2`.
+//! 3. Party 1 computes `z_1` and checks the completed signature.
+//!
+//! The messages implement `ByteEncoding` and serde, so any transport can
+//! carry them. The parties are assumed to follow the protocol: it keeps the
+//! shares apart against leakage, not against a malicious counterpart.
+
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::encoding::{check_length, versioned_vectors_from_bytes, versioned_vectors_to_bytes, scalar25519_from_bytes, ristretto_from_bytes};
+use crate::secret::SecretVec;
+use crate::{ByteEncoding, SecretEncoding, Error, Params, OKAMOTO_BASE_POINT2 as BASE_POINT2, HashTildeVersion, aggregate_curve25519, refresh_share_curve25519, sample_curve25519_lambda_with_rng, hash_tilde_curve25519, curve25519_scalar_list_to_bytes};
+use super::{Signature, SigningKey, VerifyingKey, challenge, try_verify};
+
+/// One share with the coefficient vector it is aggregated with. Encoded as
+/// `n` (u64 LE), the share and its coefficients as `n` 32-byte scalars each,
+/// and the hash-tilde version byte.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Share {
+    sk: SecretVec<Scalar>,
+    coefficients: SecretVec<Scalar>,
+    version: HashTildeVersion,
+}
+
+impl Share {
+    /// Re-randomizes the share without changing its aggregate, so each party
+    /// can refresh on its own. Panics if `n < 2`.
+    pub fn refresh<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Share {
+        let sk = refresh_share_curve25519(&self.sk, &self.coefficients, rng).into();
+        Share { sk, coefficients: self.coefficients.clone(), version: self.version }
+    }
+
+    fn aggregate(&self) -> Scalar {
+        aggregate_curve25519(&self.coefficients, &self.sk)
+    }
+}
+
+impl SigningKey {
+    /// The shares of party 1 and party 2, whose coefficients and nonces are
+    /// derived under `params.hash_version`.
+    pub fn split(&self, params: &Params) -> (Share, Share) {
+        let version = params.hash_version;
+        let (a_list, b_list) = self.coefficients(version);
+        (
+            Share { sk: self.sk1.clone(), coefficients: SecretVec::from_slice(&a_list), version },
+            Share { sk: self.sk2.clone(), coefficients: SecretVec::from_slice(&b_list), version },
+        )
+    }
+}
+
+impl std::fmt::Debug for Share {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Share(n = {})", self.sk.len())
+    }
+}
+
+impl ByteEncoding for Share {
+    fn to_bytes(&self) -> Vec<u8> {
+        versioned_vectors_to_bytes(&[&self.sk[..], &self.coefficients[..]], self.version, Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (mut vectors, version) = versioned_vectors_from_bytes(bytes, 2, scalar25519_from_bytes)?;
+        let coefficients = vectors.pop().unwrap().into();
+        Ok(Share { sk: vectors.pop().unwrap().into(), coefficients, version })
+    }
+}
+
+impl SecretEncoding for Share {}
+
+// The nonce of one party, aggregated from a fresh nonce vector like the `d`
+// (`tag = 0`) and `e` (`tag = 1`) halves of a one-party signature.
+fn nonce<R: RngCore + CryptoRng>(n: usize, version: HashTildeVersion, tag: u8, rng: &mut R) -> Scalar {
+    let r_list = Zeroizing::new(sample_curve25519_lambda_with_rng(n, rng));
+    let mut r_hash = curve25519_scalar_list_to_bytes(&r_list);
+    r_hash.push(tag);
+    let coefficients = Zeroizing::new(hash_tilde_curve25519(&r_hash, n, false, version));
+    aggregate_curve25519(&coefficients, &r_list)
+}
+
+/// Party 1 waiting for `Round2`.
+pub struct Party1 {
+    pk: VerifyingKey,
+    msg: Vec<u8>,
+    x_1: Scalar,
+    k_1: Scalar,
+    r_1: RistrettoPoint,
+}
+
+impl Party1 {
+    pub fn new<R: RngCore + CryptoRng>(share: &Share, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> (Party1, Round1) {
+        let k_1 = nonce(share.sk.len(), share.version, 0, rng);
+        let r_1 = RISTRETTO_BASEPOINT_POINT * k_1;
+        (Party1 { pk: *pk, msg: msg.to_vec(), x_1: share.aggregate(), k_1, r_1 }, Round1 { r_1 })
+    }
+
+    /// Completes the signature, failing with `Error::SignatureInvalid` if it
+    /// does not verify, e.g. because the shares do not belong to `pk`.
+    pub fn finish(self, round2: &Round2) -> Result<Signature, Error> {
+        let c = challenge(&self.pk.0, &self.msg, &(self.r_1 + round2.r_2));
+        let signature = Signature { c, z_1: self.k_1 + c * self.x_1, z_2: round2.z_2 };
+        try_verify(&self.pk.0, &self.msg, &signature.into())?;
+        Ok(signature)
+    }
+}
+
+impl Drop for Party1 {
+    fn drop(&mut self) {
+        self.x_1.zeroize();
+        self.k_1.zeroize();
+    }
+}
+
+/// Party 2 waiting for `Round1`.
+pub struct Party2 {
+    pk: VerifyingKey,
+    msg: Vec<u8>,
+    x_2: Scalar,
+    n: usize,
+    version: HashTildeVersion,
+}
+
+impl Party2 {
+    pub fn new(share: &Share, pk: &VerifyingKey, msg: &[u8]) -> Party2 {
+        Party2 { pk: *pk, msg: msg.to_vec(), x_2: share.aggregate(), n: share.sk.len(), version: share.version }
+    }
+
+    pub fn receive<R: RngCore + CryptoRng>(self, round1: &Round1, rng: &mut R) -> Round2 {
+        let k_2 = Zeroizing::new(nonce(self.n, self.version, 1, rng));
+        let r_2 = *BASE_POINT2 * *k_2;
+        let c = challenge(&self.pk.0, &self.msg, &(round1.r_1 + r_2));
+        Round2 { r_2, z_2: *k_2 + c * self.x_2 }
+    }
+}
+
+impl Drop for Party2 {
+    fn drop(&mut self) {
+        self.x_2.zeroize();
+    }
+}
+
+/// `R_1`, 32 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Round1 {
+    r_1: RistrettoPoint,
+}
+
+impl Round1 {
+    pub const BYTE_LEN: usize = 32;
+}
+
+impl ByteEncoding for Round1 {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.r_1.compress().to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Round1 { r_1: ristretto_from_bytes(bytes)? })
+    }
+}
+
+/// `R_2 || z_2`, 64 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Round2 {
+    r_2: RistrettoPoint,
+    z_2: Scalar,
+}
+
+impl Round2 {
+    pub const BYTE_LEN: usize = 64;
+}
+
+impl ByteEncoding for Round2 {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.r_2.compress().as_bytes());
+        bytes.extend_from_slice(self.z_2.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Round2 { r_2: ristretto_from_bytes(&bytes[..32])?, z_2: scalar25519_from_bytes(&bytes[32..])? })
+    }
+}
+
+impl_serde_via_bytes!(Share);
+impl_serde_via_bytes!(Round1);
+impl_serde_via_bytes!(Round2);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SignatureScheme;
+    use crate::okamoto_cml::{OkamotoCml, verify};
+    use rand::thread_rng;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    #[test]
+    fn test_lr_okamoto_cml_two_party() {
+        let params = Params::new(50);
+        let msg = b"Hello, world!";
+        let (sk, pk) = OkamotoCml::keygen(&params);
+        let (share1, share2) = sk.split(&params);
+        let share2 = share2.refresh(&mut thread_rng()).to_bytes();
+
+        // Party 2 runs on its own thread and only ever sees bytes.
+        let (to_party2, from_party1) = channel::<Vec<u8>>();
+        let (to_party1, from_party2) = channel::<Vec<u8>>();
+        let pk_bytes = pk.to_bytes();
+        let party2 = thread::spawn(move || {
+            let share = Share::from_bytes(&share2).unwrap();
+            let pk = VerifyingKey::from_bytes(&pk_bytes).unwrap();
+            let round1 = Round1::from_bytes(&from_party1.recv().unwrap()).unwrap();
+            let round2 = Party2::new(&share, &pk, msg).receive(&round1, &mut thread_rng());
+            to_party1.send(round2.to_bytes()).unwrap();
+        });
+
+        let (party1, round1) = Party1::new(&share1, &pk, msg, &mut thread_rng());
+        to_party2.send(round1.to_bytes()).unwrap();
+        let round2 = Round2::from_bytes(&from_party2.recv().unwrap()).unwrap();
+        let signature = party1.finish(&round2).unwrap();
+        party2.join().unwrap();
+
+        assert!(verify(&pk.0, msg, &signature.into()));
+        assert!(OkamotoCml::verify(&params, &pk, msg, &signature));
+
+        // Shares of another key do not combine.
+        let (other, _) = OkamotoCml::keygen(&params);
+        let (party1, round1) = Party1::new(&share1, &pk, msg, &mut thread_rng());
+        let round2 = Party2::new(&other.split(&params).1, &pk, msg).receive(&round1, &mut thread_rng());
+        assert_eq!(party1.finish(&round2), Err(Error::SignatureInvalid));
+    }
+
+    #[test]
+    fn test_lr_okamoto_cml_two_party_uses_share_version() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let legacy = Params { n: 20, hash_version: HashTildeVersion::V0 };
+        let msg = b"Hello, world!";
+        let (sk, pk) = OkamotoCml::keygen(&legacy);
+        let (share1, _) = sk.split(&legacy);
+        let (current, _) = sk.split(&Params::new(20));
+        let (_, round1) = Party1::new(&share1, &pk, msg, &mut ChaCha20Rng::from_seed([7u8; 32]));
+        let (_, other) = Party1::new(&current, &pk, msg, &mut ChaCha20Rng::from_seed([7u8; 32]));
+        // The nonce coefficients follow the version of the share.
+        assert_ne!(round1, other);
+    }
+}
@@ -0,0 +1,393 @@
+//! LR-Okamoto with `m` generators.
+//!
+//! The secret vector is aggregated into `m` secrets `x_i`, the public key is
+//! `g_1^x_1 ... g_m^x_m`, and a signature is the challenge with an `m`-vector
+//! of responses. A larger `m` leaves more of the key's entropy hidden behind
+//! its representation, at a cost linear in `m` for signing and verifying.
+//!
+//! `g_1` and `g_2` are the generators of `okamoto_aim`, and the coefficients
+//! of the `i`-th secret are derived with the tag `i - 1`, as there. With
+//! `m = 2` keys and signatures are therefore those of `okamoto_aim`.
+
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::traits::{MultiscalarMul, VartimeMultiscalarMul};
+use sha2::{Digest, Sha512};
+use rand::{CryptoRng, RngCore, thread_rng};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::encoding::{check_length, scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar25519_from_bytes, ristretto_from_bytes};
+use super::secret::SecretVec;
+use super::{ByteEncoding, SecretEncoding, Error, OKAMOTO_BASE_POINT2 as BASE_POINT2, SignatureScheme, HashTildeVersion, assert_nonempty, aggregate_curve25519, sample_curve25519_lambda_with_rng, hash_tilde_curve25519, curve25519_scalar_list_to_bytes, validate_canonical, validate_ristretto_public_key};
+
+/// The largest supported number of generators, one per value of the tag byte.
+pub const MAX_BASES: usize = 256;
+
+/// Domain string from which the generators after the first two are derived.
+pub const BASE_POINT_DOMAIN: &[u8] = b"practical-lr-signature/okamoto-multi/base-point/v1";
+
+lazy_static::lazy_static! {
+    static ref BASE_POINTS: Vec<RistrettoPoint> = (0..MAX_BASES).map(|i| match i {
+        0 => RISTRETTO_BASEPOINT_POINT,
+        1 => *BASE_POINT2,
+        _ => RistrettoPoint::from_hash(Sha512::new().chain(BASE_POINT_DOMAIN).chain((i as u64).to_le_bytes())),
+    }).collect();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiParams {
+    pub n: usize,
+    pub m: usize,
+    pub hash_version: HashTildeVersion,
+}
+
+impl MultiParams {
+    /// Panics unless `1 <= m <= MAX_BASES`.
+    pub fn new(n: usize, m: usize) -> Self {
+        check_bases(m);
+        MultiParams { n, m, hash_version: HashTildeVersion::default() }
+    }
+}
+
+fn check_bases(m: usize) {
+    assert!(validate_bases(m).is_ok(), "LR-Okamoto needs between 1 and {} generators, got {}", MAX_BASES, m);
+}
+
+fn validate_bases(m: usize) -> Result<(), Error> {
+    if !(1..=MAX_BASES).contains(&m) {
+        return Err(Error::UnsupportedBaseCount { m });
+    }
+    Ok(())
+}
+
+/// The first `m` generators: the Ristretto basepoint, the `okamoto_aim`
+/// second generator, then points hashed from `BASE_POINT_DOMAIN` and their
+/// index, so that nobody knows a relation between them.
+///
+/// Panics unless `1 <= m <= MAX_BASES`.
+pub fn generators(m: usize) -> &'static [RistrettoPoint] {
+    check_bases(m);
+    &BASE_POINTS[..m]
+}
+
+pub fn keygen(n: usize, m: usize) -> (Vec<Scalar>, RistrettoPoint) {
+    keygen_with_rng(n, m, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, m: usize, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    keygen_inner(&MultiParams::new(n, m), rng)
+}
+
+pub fn sign(pk: &RistrettoPoint, sk: &[Scalar], m: usize, msg: &[u8]) -> (Scalar, Vec<Scalar>) {
+    sign_with_rng(pk, sk, m, msg, &mut thread_rng())
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pk: &RistrettoPoint, sk: &[Scalar], m: usize, msg: &[u8], rng: &mut R) -> (Scalar, Vec<Scalar>) {
+    sign_inner(&expand_inner(HashTildeVersion::default(), sk, m), pk, msg, rng)
+}
+
+pub fn verify(pk: &RistrettoPoint, m: usize, msg: &[u8], signature: &(Scalar, Vec<Scalar>)) -> bool {
+    try_verify(pk, m, msg, signature).is_ok()
+}
+
+/// Recomputes the commitment `g_1^z_1 ... g_m^z_m pk^-c` with one
+/// multiscalar multiplication and checks it against the challenge.
+///
+/// Fails with `Error::UnsupportedBaseCount` unless `1 <= m <= MAX_BASES`.
+pub fn try_verify(pk: &RistrettoPoint, m: usize, msg: &[u8], signature: &(Scalar, Vec<Scalar>)) -> Result<(), Error> {
+    let (c, z) = signature;
+    validate_bases(m)?;
+    if z.len() != m {
+        return Err(Error::LengthMismatch { expected: Signature::byte_len(m), actual: Signature::byte_len(z.len()) });
+    }
+    validate_ristretto_public_key(pk)?;
+    validate_canonical(&std::iter::once(c).chain(z).collect::<Vec<_>>())?;
+    let scalars = z.iter().copied().chain(std::iter::once(-c));
+    let points = generators(m).iter().copied().chain(std::iter::once(*pk));
+    let r_point = RistrettoPoint::vartime_multiscalar_mul(scalars, points);
+
+    if c == &challenge(pk, msg, &r_point) {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+// Identical to the `okamoto_aim` challenge.
+fn challenge(pk: &RistrettoPoint, msg: &[u8], point: &RistrettoPoint) -> Scalar {
+    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
+    let mut c_hash = Sha512::default();
+    c_hash.update(pk.compress().as_bytes());
+    c_hash.update(msg_hash.as_bytes());
+    c_hash.update(point.compress().as_bytes());
+    Scalar::from_hash::<Sha512>(c_hash)
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &MultiParams, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    assert_nonempty(params.n);
+    let sk = sample_curve25519_lambda_with_rng(params.n, rng);
+    let pk = public_key_inner(params.hash_version, &sk, params.m);
+    (sk, pk)
+}
+
+// The `m` aggregates of `list`, the `i`-th with coefficients derived from its
+// encoding followed by the tag byte `i`.
+fn aggregate_all(list: &[Scalar], m: usize, version: HashTildeVersion) -> Vec<Scalar> {
+    let n = list.len();
+    let mut hash = curve25519_scalar_list_to_bytes(list);
+    hash.push(0);
+    (0..m).map(|i| {
+        *hash.last_mut().unwrap() = i as u8;
+        let coefficients = Zeroizing::new(hash_tilde_curve25519(&hash, n, false, version));
+        aggregate_curve25519(&coefficients, list)
+    }).collect()
+}
+
+fn expand_inner(version: HashTildeVersion, sk: &[Scalar], m: usize) -> ExpandedSigningKey {
+    check_bases(m);
+    assert_nonempty(sk.len());
+    ExpandedSigningKey { n: sk.len(), version, x: aggregate_all(sk, m, version) }
+}
+
+fn public_key_inner(version: HashTildeVersion, sk: &[Scalar], m: usize) -> RistrettoPoint {
+    let esk = expand_inner(version, sk, m);
+    RistrettoPoint::multiscalar_mul(&esk.x, generators(m))
+}
+
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &RistrettoPoint, msg: &[u8], rng: &mut R) -> (Scalar, Vec<Scalar>) {
+    let m = esk.x.len();
+    let r_list = Zeroizing::new(sample_curve25519_lambda_with_rng(esk.n, rng));
+    let k = Zeroizing::new(aggregate_all(&r_list, m, esk.version));
+
+    let r_point = RistrettoPoint::multiscalar_mul(k.iter(), generators(m));
+    let c = challenge(pk, msg, &r_point);
+    let z = k.iter().zip(&esk.x).map(|(k_i, x_i)| k_i + c * x_i).collect();
+
+    (c, z)
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey(pub(crate) SecretVec<Scalar>);
+
+impl SigningKey {
+    pub fn as_scalars(&self) -> &[Scalar] {
+        &self.0
+    }
+}
+
+impl From<Vec<Scalar>> for SigningKey {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, scalar25519_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The `m` aggregated secrets, with the length and hash version used to
+/// derive nonces.
+#[derive(Clone)]
+pub struct ExpandedSigningKey {
+    n: usize,
+    version: HashTildeVersion,
+    x: Vec<Scalar>,
+}
+
+impl std::fmt::Debug for ExpandedSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ExpandedSigningKey(n = {}, m = {})", self.n, self.x.len())
+    }
+}
+
+impl Drop for ExpandedSigningKey {
+    fn drop(&mut self) {
+        self.x.zeroize();
+    }
+}
+
+/// Compressed Ristretto point, 32 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(pub RistrettoPoint);
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 32;
+
+    /// Rejects the identity. Every Ristretto point lies in the prime-order
+    /// group, so no subgroup check is needed.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_ristretto_public_key(&self.0)
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.compress().to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(ristretto_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `c || z_1 || ... || z_m`, `m + 1` canonical scalars. `m` is read from the
+/// length and checked against `MultiParams::m` on verification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub c: Scalar,
+    pub z: Vec<Scalar>,
+}
+
+impl Signature {
+    pub fn byte_len(m: usize) -> usize {
+        32 * (m + 1)
+    }
+}
+
+impl From<(Scalar, Vec<Scalar>)> for Signature {
+    fn from((c, z): (Scalar, Vec<Scalar>)) -> Self {
+        Signature { c, z }
+    }
+}
+
+impl From<Signature> for (Scalar, Vec<Scalar>) {
+    fn from(signature: Signature) -> Self {
+        (signature.c, signature.z)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::byte_len(self.z.len()));
+        bytes.extend_from_slice(self.c.as_bytes());
+        for z_i in &self.z {
+            bytes.extend_from_slice(z_i.as_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::byte_len((bytes.len() / 32).max(2) - 1))?;
+        let scalars = bytes.chunks(32).map(scalar25519_from_bytes).collect::<Result<Vec<_>, _>>()?;
+        Ok(Signature { c: scalars[0], z: scalars[1..].to_vec() })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+
+pub struct OkamotoMulti;
+
+impl SignatureScheme for OkamotoMulti {
+    const NAME: &'static str = "LR-Okamoto-Multi";
+
+    type Params = MultiParams;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &MultiParams, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &MultiParams, sk: &SigningKey) -> VerifyingKey {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0, params.m))
+    }
+
+    fn expand(params: &MultiParams, sk: &SigningKey) -> ExpandedSigningKey {
+        expand_inner(params.hash_version, &sk.0, params.m)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        Ok(sign_inner(esk, &pk.0, msg, rng).into())
+    }
+
+    fn try_verify(params: &MultiParams, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&pk.0, params.m, msg, &(signature.c, signature.z.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{okamoto_aim, PublicParams};
+
+    #[test]
+    fn test_lr_okamoto_multi() {
+        let msg = b"Hello, world!";
+        for &m in &[1, 3, 8] {
+            let params = MultiParams::new(20, m);
+            let (sk, pk) = OkamotoMulti::keygen(&params);
+            let signature = OkamotoMulti::sign(&params, &sk, &pk, msg);
+            assert_eq!(signature.z.len(), m);
+            assert!(OkamotoMulti::verify(&params, &pk, msg, &signature));
+            assert!(!OkamotoMulti::verify(&params, &pk, b"forged", &signature));
+
+            let decoded = Signature::from_bytes(&signature.to_bytes()).unwrap();
+            assert_eq!(decoded, signature);
+            assert_eq!(SigningKey::from_bytes(&sk.to_bytes()).unwrap(), sk);
+        }
+    }
+
+    #[test]
+    fn test_lr_okamoto_multi_rejects_wrong_m() {
+        let (sk, pk) = keygen(20, 4);
+        let signature = sign(&pk, &sk, 4, b"msg");
+        assert!(verify(&pk, 4, b"msg", &signature));
+        assert_eq!(try_verify(&pk, 3, b"msg", &signature), Err(Error::LengthMismatch { expected: 128, actual: 160 }));
+        assert_eq!(Signature::from_bytes(&[0u8; 97]), Err(Error::LengthMismatch { expected: 96, actual: 97 }));
+        assert_eq!(Signature::from_bytes(&[0u8; 32]), Err(Error::LengthMismatch { expected: 64, actual: 32 }));
+
+        let params = MultiParams { m: 0, ..MultiParams::new(20, 4) };
+        let signature = Signature { c: Scalar::zero(), z: Vec::new() };
+        assert_eq!(OkamotoMulti::try_verify(&params, &VerifyingKey(pk), b"msg", &signature), Err(Error::UnsupportedBaseCount { m: 0 }));
+        let params = MultiParams { m: MAX_BASES + 1, ..params };
+        assert!(!OkamotoMulti::verify(&params, &VerifyingKey(pk), b"msg", &signature));
+    }
+
+    #[test]
+    fn test_lr_okamoto_multi_two_bases_is_okamoto_aim() {
+        let gens = generators(MAX_BASES);
+        assert_eq!(gens[..2], [PublicParams::standard().g1, PublicParams::standard().g2]);
+        assert!(gens.iter().enumerate().all(|(i, g)| gens[..i].iter().all(|h| h != g)));
+
+        let (sk, pk) = okamoto_aim::keygen(20);
+        assert_eq!(public_key_inner(HashTildeVersion::default(), &sk, 2), pk);
+        let params = MultiParams::new(20, 2);
+        let signature = OkamotoMulti::sign_deterministic(&params, &SigningKey::from(sk.clone()), &VerifyingKey(pk), b"msg");
+        let (c, z) = signature.into();
+        assert!(okamoto_aim::verify(&pk, b"msg", &(c, z[0], z[1])));
+        let (c, z_1, z_2) = okamoto_aim::sign(&pk, &sk, b"msg");
+        assert!(verify(&pk, 2, b"msg", &(c, vec![z_1, z_2])));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lr_okamoto_multi_too_many_bases() {
+        MultiParams::new(20, MAX_BASES + 1);
+    }
+}
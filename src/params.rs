@@ -0,0 +1,178 @@
+//! Choosing the vector length `n` from a security level and a leakage bound.
+//!
+//! Every secret vector entry is sampled uniformly from `[0, LAM)`, so a vector
+//! of length `n` has `n * log2(LAM)` bits of min-entropy, and leaking `l` bits
+//! about it leaves at least `n * log2(LAM) - l` bits of average min-entropy
+//! (Dodis, Ostrovsky, Reyzin and Smith, "Fuzzy extractors", SIAM J. Comput.
+//! 38(1), 2008, Lemma 2.2(b)).
+//!
+//! The signing secret is the aggregate `<a, sk> mod q`. `a` comes from
+//! `hash_tilde_*`, which we model as a random oracle, and for a uniform `a`
+//! the inner product mod the prime `q` is a universal hash of `sk`, since
+//! entries below `LAM < q` never differ by a multiple of `q`. The average-case
+//! leftover hash lemma (same paper, Lemma 2.4) then makes the aggregate
+//! `2^-security`-close to uniform given the leakage once the remaining entropy
+//! is at least `log2(q) + 2 * security` bits, which is `required_entropy`.
+//! `q` is the scalar field order: 253 bits for Curve25519, 255 for BLS12-381
+//! and 256 for secp256k1 and P-256.
+//!
+//! The CML schemes keep two shares and refresh them between periods, so a
+//! share is exposed to the leakage of the period before and after a refresh.
+//! Each share gets twice the AIM length. The pair then has `2 * (required +
+//! l)` bits of entropy, which covers the `2 * l` bits leaked over two periods
+//! and the `2 * log2(q) + 2 * security` bits that the lemma needs to extract
+//! both aggregates at once. The scheme-independent `bls`, `ecdsa` and
+//! `schnorr` modules are used with either length.
+//!
+//! At 128-bit security the benchmarks use leakage rates 0, 1/2 and 9/10:
+//!
+//! | Curve | Model | rate 0 | rate 1/2 | rate 9/10 |
+//! |---|---|---|---|---|
+//! | BLS12-381 | AIM | 73 | 146 | 730 |
+//! | BLS12-381 | CML | 146 | 292 | 1460 |
+//! | Curve25519 | AIM | 73 | 146 | 728 |
+//! | Curve25519 | CML | 146 | 292 | 1456 |
+
+use super::LAM;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Curve25519,
+    Bls12_381,
+    Secp256k1,
+    P256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// Auxiliary-input leakage on a single secret vector.
+    Aim,
+    /// Continual memory leakage on two refreshed shares.
+    Cml,
+}
+
+/// How much an adversary may learn about a secret vector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Leakage {
+    /// An absolute number of bits.
+    Bits(usize),
+    /// A fraction in `[0, 1)` of the vector's entropy.
+    Rate(f64),
+}
+
+pub const DEFAULT_SECURITY: u32 = 128;
+
+const CURVE25519_SCALAR_BITS: usize = 253;
+const BLS12_381_SCALAR_BITS: usize = 255;
+const SECP256K1_SCALAR_BITS: usize = 256;
+const P256_SCALAR_BITS: usize = 256;
+
+fn entry_bits() -> f64 {
+    (LAM as f64).log2()
+}
+
+/// Entropy in bits that must remain in an AIM secret vector after leakage,
+/// `log2(q) + 2 * security` by the leftover hash lemma.
+pub fn required_entropy(curve: Curve, security: u32) -> usize {
+    let scalar_bits = match curve {
+        Curve::Curve25519 => CURVE25519_SCALAR_BITS,
+        Curve::Bls12_381 => BLS12_381_SCALAR_BITS,
+        Curve::Secp256k1 => SECP256K1_SCALAR_BITS,
+        Curve::P256 => P256_SCALAR_BITS,
+    };
+    scalar_bits + 2 * security as usize
+}
+
+/// The smallest `n` that tolerates `leakage` at `security` bits, or `None` if
+/// the rate is not in `[0, 1)`.
+pub fn min_n(curve: Curve, model: Model, security: u32, leakage: Leakage) -> Option<usize> {
+    let required = required_entropy(curve, security) as f64;
+    let n = match leakage {
+        Leakage::Bits(bits) => ((required + bits as f64) / entry_bits()).ceil(),
+        // `1.0 - rate` is inexact for most rates, so allow for rounding error
+        // before taking the ceiling.
+        Leakage::Rate(rate) if (0.0..1.0).contains(&rate) => (required / ((1.0 - rate) * entry_bits()) - 1e-9).ceil(),
+        Leakage::Rate(_) => return None,
+    } as usize;
+    Some(match model {
+        Model::Aim => n,
+        Model::Cml => 2 * n,
+    })
+}
+
+/// The number of bits a key of length `n` may leak at `security` bits, or
+/// `None` if `n` is too short for any leakage.
+pub fn tolerated_bits(curve: Curve, model: Model, security: u32, n: usize) -> Option<usize> {
+    let n = match model {
+        Model::Aim => n,
+        Model::Cml => n / 2,
+    };
+    let entropy = (n as f64 * entry_bits()) as usize;
+    entropy.checked_sub(required_entropy(curve, security))
+}
+
+/// `tolerated_bits` as a fraction of the key's entropy.
+pub fn tolerated_rate(curve: Curve, model: Model, security: u32, n: usize) -> Option<f64> {
+    let bits = tolerated_bits(curve, model, security, n)?;
+    let n = match model {
+        Model::Aim => n,
+        Model::Cml => n / 2,
+    };
+    Some(bits as f64 / (n as f64 * entry_bits()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_bits() {
+        use ff::PrimeField;
+        assert_eq!(BLS12_381_SCALAR_BITS, bls12_381::Scalar::NUM_BITS as usize);
+        assert_eq!(SECP256K1_SCALAR_BITS, k256::Scalar::NUM_BITS as usize);
+        assert_eq!(P256_SCALAR_BITS, p256::Scalar::NUM_BITS as usize);
+        // 2^252 + 27742317777372353535851937790883648493
+        let order = curve25519_dalek::constants::BASEPOINT_ORDER.to_bytes();
+        assert_eq!(CURVE25519_SCALAR_BITS, 256 - order[31].leading_zeros() as usize);
+    }
+
+    #[test]
+    fn test_lengths() {
+        // 128-bit security, 7 bits per entry: Curve25519 needs 253 + 256 = 509
+        // bits, BLS12-381 needs 255 + 256 = 511.
+        let expected = [
+            (Curve::Curve25519, Leakage::Bits(0), 73),
+            (Curve::Curve25519, Leakage::Bits(2), 73),
+            (Curve::Curve25519, Leakage::Bits(3), 74),
+            (Curve::Bls12_381, Leakage::Bits(0), 73),
+            (Curve::Bls12_381, Leakage::Bits(1), 74),
+            (Curve::Curve25519, Leakage::Rate(0.5), 146),
+            (Curve::Bls12_381, Leakage::Rate(0.9), 730),
+        ];
+        for &(curve, leakage, n) in expected.iter() {
+            assert_eq!(min_n(curve, Model::Aim, DEFAULT_SECURITY, leakage), Some(n));
+            assert_eq!(min_n(curve, Model::Cml, DEFAULT_SECURITY, leakage), Some(2 * n));
+        }
+        assert_eq!(required_entropy(Curve::Curve25519, 80), 413);
+    }
+
+    #[test]
+    fn test_min_n_inverts_tolerated() {
+        for &curve in [Curve::Curve25519, Curve::Bls12_381, Curve::Secp256k1, Curve::P256].iter() {
+            for &model in [Model::Aim, Model::Cml].iter() {
+                for &bits in [0, 1, 100, 5000].iter() {
+                    let n = min_n(curve, model, DEFAULT_SECURITY, Leakage::Bits(bits)).unwrap();
+                    assert!(tolerated_bits(curve, model, DEFAULT_SECURITY, n).unwrap() >= bits);
+                    let shorter = tolerated_bits(curve, model, DEFAULT_SECURITY, n - 1);
+                    assert!(!matches!(shorter, Some(b) if b >= bits));
+                }
+                for &rate in [0.0, 0.25, 0.5, 0.9].iter() {
+                    let n = min_n(curve, model, DEFAULT_SECURITY, Leakage::Rate(rate)).unwrap();
+                    assert!(tolerated_rate(curve, model, DEFAULT_SECURITY, n).unwrap() >= rate);
+                }
+            }
+        }
+        assert_eq!(min_n(Curve::Curve25519, Model::Aim, DEFAULT_SECURITY, Leakage::Rate(1.0)), None);
+        assert_eq!(tolerated_bits(Curve::Curve25519, Model::Aim, DEFAULT_SECURITY, 1), None);
+    }
+}
@@ -1,56 +1,552 @@
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::ristretto::RistrettoPoint;
-use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::constants::{ED25519_BASEPOINT_TABLE, RISTRETTO_BASEPOINT_POINT};
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
 use sha2::{Digest, Sha512};
+use rand::{CryptoRng, RngCore, thread_rng};
 
-use super::{aggregate_curve25519, sample_curve25519_lambda, hash_tilde_curve25519, curve25519_scalar_list_to_bytes};
+use super::batch;
+use super::encoding::{check_length, scalar_vectors_from_bytes, scalar_vectors_to_bytes, scalar25519_from_bytes, ristretto_from_bytes};
+use super::secret::SecretVec;
+use super::generic::{self, Ristretto, RistrettoScalar, from_ristretto_scalars, to_ristretto_scalars};
+use super::{ByteEncoding, SecretEncoding, Error, Params, SignatureScheme, HashTildeVersion, assert_nonempty, validate_canonical, validate_ristretto_public_key};
 
 pub fn keygen(n: usize) -> (Vec<Scalar>, RistrettoPoint) {
-    let sk = sample_curve25519_lambda(n);
-    let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk), n, false);
-    let pk = RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&a_list, &sk);
-    (sk, pk)
+    keygen_with_rng(n, &mut thread_rng())
+}
+
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    keygen_inner(&Params::new(n), rng)
 }
 
 pub fn sign(pk: &RistrettoPoint, sk: &[Scalar], msg: &[u8]) -> (Scalar, Scalar) {
-    let n = sk.len();
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
-    let a_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&sk), n, false);
-    let r_list = sample_curve25519_lambda(n);
-    let b_list = hash_tilde_curve25519(&curve25519_scalar_list_to_bytes(&r_list), n, true);
-    let point = RISTRETTO_BASEPOINT_POINT * aggregate_curve25519(&b_list, &r_list);
-
-    let mut c_hash = Sha512::default();
-    c_hash.update(pk.compress().as_bytes());
-    c_hash.update(msg_hash.as_bytes());
-    c_hash.update(point.compress().as_bytes());
-    let c = Scalar::from_hash::<Sha512>(c_hash);
-
-    let z = aggregate_curve25519(&b_list, &r_list) + c * aggregate_curve25519(&a_list, &sk);
+    sign_with_rng(pk, sk, msg, &mut thread_rng())
+}
+
+pub fn sign_with_rng<R: RngCore + CryptoRng>(pk: &RistrettoPoint, sk: &[Scalar], msg: &[u8], rng: &mut R) -> (Scalar, Scalar) {
+    let (_, c, z) = sign_inner(&expand_inner(HashTildeVersion::default(), sk), pk, msg, rng);
     (c, z)
 }
 
+/// Signs in the `R || z` form accepted by `verify_batch`.
+pub fn sign_batchable(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8]) -> BatchSignature {
+    sign_batchable_with_rng(esk, pk, msg, &mut thread_rng())
+}
+
+pub fn sign_batchable_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> BatchSignature {
+    let (r, _, z) = sign_inner(esk, &pk.0, msg, rng);
+    BatchSignature { r, z }
+}
+
+/// Signs as Ed25519 (RFC 8032) with the aggregated secret as the secret
+/// scalar, so that any Ed25519 verifier accepts the signature under
+/// `esk.ed25519_public_key()`. Only the nonce derivation differs from Ed25519,
+/// and it is drawn from a secret vector as in `sign`.
+pub fn sign_ed25519(esk: &ExpandedSigningKey, pk: &Ed25519VerifyingKey, msg: &[u8]) -> Ed25519Signature {
+    sign_ed25519_with_rng(esk, pk, msg, &mut thread_rng())
+}
+
+pub fn sign_ed25519_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &Ed25519VerifyingKey, msg: &[u8], rng: &mut R) -> Ed25519Signature {
+    let nonce = generic::schnorr::nonce(esk, rng);
+    let r = (&nonce.0 * &ED25519_BASEPOINT_TABLE).compress();
+    let s = nonce.0 + ed25519_challenge(&r, &pk.bytes, msg) * esk.x.0;
+    Ed25519Signature { r, s }
+}
+
+pub fn verify_ed25519(pk: &Ed25519VerifyingKey, msg: &[u8], signature: &Ed25519Signature) -> bool {
+    try_verify_ed25519(pk, msg, signature).is_ok()
+}
+
+/// The cofactorless RFC 8032 check `[s]B == R + [k]A`, with `s` required to
+/// be canonical and `A` not of small order.
+pub fn try_verify_ed25519(pk: &Ed25519VerifyingKey, msg: &[u8], signature: &Ed25519Signature) -> Result<(), Error> {
+    validate_ed25519_public_key(&pk.point)?;
+    validate_canonical(&[&signature.s])?;
+    let k = ed25519_challenge(&signature.r, &pk.bytes, msg);
+    let r = EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-pk.point, &signature.s);
+    if r.compress() == signature.r {
+        Ok(())
+    } else {
+        Err(Error::SignatureInvalid)
+    }
+}
+
+// A small-order key, the identity included, lets one signature verify for
+// every message.
+fn validate_ed25519_public_key(point: &EdwardsPoint) -> Result<(), Error> {
+    if point.is_small_order() {
+        return Err(Error::IdentityPublicKey);
+    }
+    Ok(())
+}
+
+fn ed25519_challenge(r: &CompressedEdwardsY, pk: &CompressedEdwardsY, msg: &[u8]) -> Scalar {
+    let mut k_hash = Sha512::default();
+    k_hash.update(r.as_bytes());
+    k_hash.update(pk.as_bytes());
+    k_hash.update(msg);
+    Scalar::from_hash(k_hash)
+}
+
 pub fn verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar)) -> bool {
+    try_verify(pk, msg, signature).is_ok()
+}
+
+pub fn try_verify(pk: &RistrettoPoint, msg: &[u8], signature: &(Scalar, Scalar)) -> Result<(), Error> {
     let (c, z) = signature;
-    let msg_hash = Scalar::hash_from_bytes::<Sha512>(msg);
+    validate_ristretto_public_key(pk)?;
+    validate_canonical(&[c, z])?;
+    generic::schnorr::try_verify(&Ristretto(*pk), msg, &(RistrettoScalar(*c), RistrettoScalar(*z)))
+}
+
+/// Verifies every `(pk, msg, signature)` entry, returning the indices of the
+/// invalid ones.
+pub fn verify_batch(entries: &[(VerifyingKey, &[u8], BatchSignature)]) -> Result<(), Vec<usize>> {
+    verify_batch_with_rng(entries, &mut thread_rng())
+}
+
+pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(entries: &[(VerifyingKey, &[u8], BatchSignature)], rng: &mut R) -> Result<(), Vec<usize>> {
+    let equations: Vec<batch::Equation> = entries.iter().map(|(pk, msg, signature)| batch::Equation {
+        pk: pk.0,
+        r: signature.r,
+        c: challenge(&pk.0, msg, &signature.r),
+        z: vec![signature.z],
+        valid: signature.validate(pk).is_ok(),
+    }).collect();
+    batch::verify_with_rng(&[RISTRETTO_BASEPOINT_POINT], &equations, rng)
+}
+
+fn challenge(pk: &RistrettoPoint, msg: &[u8], point: &RistrettoPoint) -> Scalar {
+    generic::schnorr::challenge(&Ristretto(*pk), msg, &Ristretto(*point)).0
+}
+
+fn keygen_inner<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (Vec<Scalar>, RistrettoPoint) {
+    let (sk, pk) = generic::schnorr::keygen_inner::<Ristretto, R>(params, rng);
+    (from_ristretto_scalars(sk), pk.0)
+}
+
+fn expand_inner(version: HashTildeVersion, sk: &[Scalar]) -> ExpandedSigningKey {
+    generic::schnorr::expand_inner(version, to_ristretto_scalars(sk))
+}
+
+fn public_key_inner(version: HashTildeVersion, sk: &[Scalar]) -> RistrettoPoint {
+    generic::schnorr::public_key_inner::<Ristretto>(version, to_ristretto_scalars(sk)).0
+}
+
+fn sign_inner<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &RistrettoPoint, msg: &[u8], rng: &mut R) -> (RistrettoPoint, Scalar, Scalar) {
+    let (point, c, z) = generic::schnorr::sign_inner(esk, &Ristretto(*pk), msg, rng);
+    (point.0, c.0, z.0)
+}
+
+/// Secret vector, encoded as `n` (u64 LE) followed by `n` 32-byte scalars.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningKey(pub(crate) SecretVec<Scalar>);
+
+impl SigningKey {
+    pub fn as_scalars(&self) -> &[Scalar] {
+        &self.0
+    }
+}
+
+impl From<Vec<Scalar>> for SigningKey {
+    /// Panics if `sk` is empty.
+    fn from(sk: Vec<Scalar>) -> Self {
+        assert_nonempty(sk.len());
+        SigningKey(sk.into())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SigningKey(n = {})", self.0.len())
+    }
+}
+
+impl ByteEncoding for SigningKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        scalar_vectors_to_bytes(&[&self.0[..]], Scalar::to_bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut vectors = scalar_vectors_from_bytes(bytes, 1, scalar25519_from_bytes)?;
+        Ok(SigningKey(vectors.pop().unwrap().into()))
+    }
+}
+
+impl SecretEncoding for SigningKey {}
+
+/// The aggregated secret `<a, sk>` of `generic::schnorr`.
+pub type ExpandedSigningKey = generic::schnorr::ExpandedSigningKey<Ristretto>;
+
+impl ExpandedSigningKey {
+    /// The Ed25519 public key `x * B` of the aggregated secret, for `sign_ed25519`.
+    pub fn ed25519_public_key(&self) -> Ed25519VerifyingKey {
+        let point = &self.x.0 * &ED25519_BASEPOINT_TABLE;
+        Ed25519VerifyingKey { point, bytes: point.compress() }
+    }
+}
+
+/// Compressed Ristretto point, 32 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyingKey(pub RistrettoPoint);
+
+impl VerifyingKey {
+    pub const BYTE_LEN: usize = 32;
+
+    /// Rejects the identity. Every Ristretto point lies in the prime-order
+    /// group, so no subgroup check is needed.
+    pub fn validate(&self) -> Result<(), Error> {
+        validate_ristretto_public_key(&self.0)
+    }
+}
+
+impl ByteEncoding for VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.compress().to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let pk = VerifyingKey(ristretto_from_bytes(bytes)?);
+        pk.validate()?;
+        Ok(pk)
+    }
+}
+
+/// `c || z`, two canonical scalars, 64 bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub c: Scalar,
+    pub z: Scalar,
+}
+
+impl Signature {
+    pub const BYTE_LEN: usize = 64;
+}
+
+impl From<(Scalar, Scalar)> for Signature {
+    fn from((c, z): (Scalar, Scalar)) -> Self {
+        Signature { c, z }
+    }
+}
+
+impl From<Signature> for (Scalar, Scalar) {
+    fn from(signature: Signature) -> Self {
+        (signature.c, signature.z)
+    }
+}
+
+impl ByteEncoding for Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.c.as_bytes());
+        bytes.extend_from_slice(self.z.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Signature { c: scalar25519_from_bytes(&bytes[0..32])?, z: scalar25519_from_bytes(&bytes[32..64])? })
+    }
+}
+
+/// `R || z`, the compressed commitment and the response, 64 bytes. Unlike
+/// `Signature` it can be checked by `verify_batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchSignature {
+    pub r: RistrettoPoint,
+    pub z: Scalar,
+}
+
+impl BatchSignature {
+    pub const BYTE_LEN: usize = 64;
+
+    /// Recovers the commitment of a `c || z` signature, at the cost of a verification.
+    pub fn from_signature(pk: &VerifyingKey, signature: &Signature) -> Self {
+        BatchSignature { r: RISTRETTO_BASEPOINT_POINT * signature.z - pk.0 * signature.c, z: signature.z }
+    }
+
+    pub fn to_signature(&self, pk: &VerifyingKey, msg: &[u8]) -> Signature {
+        Signature { c: challenge(&pk.0, msg, &self.r), z: self.z }
+    }
+
+    fn validate(&self, pk: &VerifyingKey) -> Result<(), Error> {
+        pk.validate()?;
+        validate_canonical(&[&self.z])
+    }
+}
+
+impl ByteEncoding for BatchSignature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.r.compress().as_bytes());
+        bytes.extend_from_slice(self.z.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(BatchSignature { r: ristretto_from_bytes(&bytes[0..32])?, z: scalar25519_from_bytes(&bytes[32..64])? })
+    }
+}
+
+/// Compressed Edwards point, the standard 32-byte Ed25519 public key. The
+/// bytes it was decoded from are kept, since the challenge hashes them and a
+/// non-canonical encoding would not survive recompression. Points of small
+/// order are rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ed25519VerifyingKey {
+    point: EdwardsPoint,
+    bytes: CompressedEdwardsY,
+}
+
+impl Ed25519VerifyingKey {
+    pub const BYTE_LEN: usize = 32;
+
+    pub fn point(&self) -> &EdwardsPoint {
+        &self.point
+    }
+}
+
+impl ByteEncoding for Ed25519VerifyingKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.to_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        let bytes = CompressedEdwardsY::from_slice(bytes);
+        let point = bytes.decompress().ok_or(Error::MalformedPoint)?;
+        validate_ed25519_public_key(&point)?;
+        Ok(Ed25519VerifyingKey { point, bytes })
+    }
+}
+
+/// `R || s`, the standard 64-byte Ed25519 signature. `R` is kept compressed,
+/// as verifiers compare it byte for byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ed25519Signature {
+    pub r: CompressedEdwardsY,
+    pub s: Scalar,
+}
+
+impl Ed25519Signature {
+    pub const BYTE_LEN: usize = 64;
+}
+
+impl ByteEncoding for Ed25519Signature {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTE_LEN);
+        bytes.extend_from_slice(self.r.as_bytes());
+        bytes.extend_from_slice(self.s.as_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        check_length(bytes, Self::BYTE_LEN)?;
+        Ok(Ed25519Signature { r: CompressedEdwardsY::from_slice(&bytes[0..32]), s: scalar25519_from_bytes(&bytes[32..64])? })
+    }
+}
+
+impl_serde_via_bytes!(SigningKey);
+impl_serde_via_bytes!(VerifyingKey);
+impl_serde_via_bytes!(Signature);
+impl_serde_via_bytes!(BatchSignature);
+impl_serde_via_bytes!(Ed25519VerifyingKey);
+impl_serde_via_bytes!(Ed25519Signature);
+
+pub struct Schnorr;
+
+impl SignatureScheme for Schnorr {
+    const NAME: &'static str = "LR-Schnorr";
+
+    type Params = Params;
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = Signature;
+    type ExpandedSecretKey = ExpandedSigningKey;
 
-    let mut c_candidate_hash = Sha512::default();
-    c_candidate_hash.update(pk.compress().as_bytes());
-    c_candidate_hash.update(msg_hash.as_bytes());
-    c_candidate_hash.update((RISTRETTO_BASEPOINT_POINT * z + pk * (-c)).compress().as_bytes());
+    fn keygen_with_rng<R: RngCore + CryptoRng>(params: &Params, rng: &mut R) -> (SigningKey, VerifyingKey) {
+        let (sk, pk) = keygen_inner(params, rng);
+        (SigningKey(sk.into()), VerifyingKey(pk))
+    }
+
+    fn public_key(params: &Params, sk: &SigningKey) -> VerifyingKey {
+        VerifyingKey(public_key_inner(params.hash_version, &sk.0))
+    }
 
-    c == &Scalar::from_hash::<Sha512>(c_candidate_hash)
+    fn expand(params: &Params, sk: &SigningKey) -> ExpandedSigningKey {
+        expand_inner(params.hash_version, &sk.0)
+    }
+
+    fn try_sign_expanded_with_rng<R: RngCore + CryptoRng>(esk: &ExpandedSigningKey, pk: &VerifyingKey, msg: &[u8], rng: &mut R) -> Result<Signature, Error> {
+        let (_, c, z) = sign_inner(esk, &pk.0, msg, rng);
+        Ok(Signature { c, z })
+    }
+
+    fn try_verify(_params: &Params, pk: &VerifyingKey, msg: &[u8], signature: &Signature) -> Result<(), Error> {
+        try_verify(&pk.0, msg, &(*signature).into())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve25519_dalek::constants;
     #[test]
     fn test_lr_schnorr() {
         let n = 100;
         let msg = "Hello, world!";
         let (sk, pk) = keygen(n);
-        let signature = sign(&pk, &sk, &msg.as_bytes());
-        assert!(verify(&pk, &msg.as_bytes(), &signature))
+        let signature = sign(&pk, &sk, msg.as_bytes());
+        assert!(verify(&pk, msg.as_bytes(), &signature))
+    }
+
+    #[test]
+    fn test_lr_schnorr_legacy_keys() {
+        let params = Params { n: 100, hash_version: HashTildeVersion::V0 };
+        let msg = "Hello, world!".as_bytes();
+        let (sk, pk) = Schnorr::keygen(&params);
+        let signature = Schnorr::sign(&params, &sk, &pk, msg);
+        assert!(Schnorr::verify(&params, &pk, msg, &signature));
+        let signature = Schnorr::sign(&Params::new(100), &sk, &pk, msg);
+        assert!(!Schnorr::verify(&params, &pk, msg, &signature));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lr_schnorr_verify_batch() {
+        let params = Params::new(20);
+        let messages: Vec<Vec<u8>> = (0..8).map(|i| format!("message {}", i).into_bytes()).collect();
+        let mut entries: Vec<(VerifyingKey, &[u8], BatchSignature)> = messages.iter().map(|msg| {
+            let (sk, pk) = Schnorr::keygen(&params);
+            let signature = sign_batchable(&Schnorr::expand(&params, &sk), &pk, msg);
+            assert!(Schnorr::verify(&params, &pk, msg, &signature.to_signature(&pk, msg)));
+            (pk, msg.as_slice(), signature)
+        }).collect();
+        assert_eq!(verify_batch(&entries), Ok(()));
+        assert_eq!(verify_batch(&[]), Ok(()));
+
+        entries[2].1 = b"forged";
+        entries[5].2.z += Scalar::one();
+        assert_eq!(verify_batch(&entries), Err(vec![2, 5]));
+    }
+
+    #[test]
+    fn test_lr_schnorr_rejects_invalid_inputs() {
+        let msg = b"Hello, world!";
+        let (sk, pk) = keygen(20);
+        let (c, z) = sign(&pk, &sk, msg);
+        assert_eq!(try_verify(&RistrettoPoint::default(), msg, &(Scalar::zero(), Scalar::zero())), Err(Error::IdentityPublicKey));
+        assert_eq!(VerifyingKey(RistrettoPoint::default()).validate(), Err(Error::IdentityPublicKey));
+        assert_eq!(VerifyingKey::from_bytes(&[0u8; 32]), Err(Error::IdentityPublicKey));
+
+        // z + l is the same residue but not a canonical encoding.
+        let mut unreduced = z.to_bytes();
+        let carry = unreduced.iter_mut().zip(&constants::BASEPOINT_ORDER.to_bytes()).fold(0u16, |carry, (byte, l)| {
+            let sum = *byte as u16 + *l as u16 + carry;
+            *byte = sum as u8;
+            sum >> 8
+        });
+        assert_eq!(carry, 0);
+        let unreduced = Scalar::from_bits(unreduced);
+        assert_eq!(unreduced.reduce(), z);
+        assert_eq!(try_verify(&pk, msg, &(c, unreduced)), Err(Error::NonCanonicalScalar));
+
+        let params = Params::new(20);
+        let (sk, pk) = Schnorr::keygen(&params);
+        let signature = sign_batchable(&Schnorr::expand(&params, &sk), &pk, msg);
+        let identity = VerifyingKey(RistrettoPoint::default());
+        let forged = BatchSignature { r: RistrettoPoint::default(), z: Scalar::zero() };
+        assert_eq!(verify_batch(&[(pk, &msg[..], signature), (identity, &msg[..], forged)]), Err(vec![1]));
+    }
+
+    #[test]
+    fn test_lr_schnorr_batch_signature_conversion() {
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, pk) = Schnorr::keygen(&params);
+        let signature = Schnorr::sign(&params, &sk, &pk, msg);
+        let batch_signature = BatchSignature::from_signature(&pk, &signature);
+        assert_eq!(batch_signature.to_signature(&pk, msg), signature);
+        assert_eq!(BatchSignature::from_bytes(&batch_signature.to_bytes()), Ok(batch_signature));
+        assert_eq!(verify_batch(&[(pk, &msg[..], batch_signature)]), Ok(()));
+    }
+
+    #[test]
+    fn test_lr_schnorr_ed25519_interop() {
+        use ed25519_dalek::Verifier;
+        use std::convert::TryFrom;
+
+        let params = Params::new(20);
+        let msg = b"Hello, world!";
+        let (sk, _) = Schnorr::keygen(&params);
+        let esk = Schnorr::expand(&params, &sk);
+        let pk = esk.ed25519_public_key();
+        let signature = sign_ed25519(&esk, &pk, msg);
+        assert!(verify_ed25519(&pk, msg, &signature));
+        assert!(!verify_ed25519(&pk, b"forged", &signature));
+        assert_eq!(Ed25519Signature::from_bytes(&signature.to_bytes()), Ok(signature));
+
+        // The reference implementation accepts our signatures...
+        let reference_pk = ed25519_dalek::PublicKey::from_bytes(&pk.to_bytes()).unwrap();
+        let reference_signature = ed25519_dalek::Signature::try_from(signature.to_bytes().as_slice()).unwrap();
+        assert!(reference_pk.verify(msg, &reference_signature).is_ok());
+        assert!(reference_pk.verify_strict(msg, &reference_signature).is_ok());
+        assert!(reference_pk.verify(b"forged", &reference_signature).is_err());
+
+        // ...and we accept its signatures under the aggregated secret.
+        let reference_sk = ed25519_dalek::ExpandedSecretKey::from_bytes(&[esk.x.0.to_bytes(), [7u8; 32]].concat()).unwrap();
+        let reference_signature = reference_sk.sign(msg, &reference_pk);
+        assert!(verify_ed25519(&pk, msg, &Ed25519Signature::from_bytes(&reference_signature.to_bytes()).unwrap()));
+    }
+
+    #[test]
+    fn test_lr_schnorr_ed25519_rejects_small_order_keys() {
+        use curve25519_dalek::constants::EIGHT_TORSION;
+        use curve25519_dalek::traits::Identity;
+
+        for point in EIGHT_TORSION.iter() {
+            assert_eq!(Ed25519VerifyingKey::from_bytes(point.compress().as_bytes()), Err(Error::IdentityPublicKey));
+        }
+        // s = 0 and R = identity verify for every message under the identity.
+        let esk = ExpandedSigningKey { x: Scalar::zero().into(), ..Schnorr::expand(&Params::new(1), &Schnorr::keygen(&Params::new(1)).0) };
+        let pk = esk.ed25519_public_key();
+        let signature = Ed25519Signature { r: EdwardsPoint::identity().compress(), s: Scalar::zero() };
+        assert_eq!(try_verify_ed25519(&pk, b"Hello, world!", &signature), Err(Error::IdentityPublicKey));
+    }
+
+    #[test]
+    fn test_lr_schnorr_ed25519_keeps_key_bytes() {
+        // y + p for a small y, a non-canonical encoding that decompresses to
+        // the same point as y itself.
+        let encode = |y: u8, canonical: bool| {
+            let mut bytes = if canonical { [0u8; 32] } else { [0xffu8; 32] };
+            bytes[0] = if canonical { y } else { 0xed + y };
+            bytes[31] = if canonical { 0 } else { 0x7f };
+            bytes
+        };
+        let y = (2..19).find(|&y| Ed25519VerifyingKey::from_bytes(&encode(y, true)).is_ok()).unwrap();
+        let canonical = Ed25519VerifyingKey::from_bytes(&encode(y, true)).unwrap();
+        let non_canonical = Ed25519VerifyingKey::from_bytes(&encode(y, false)).unwrap();
+        assert_eq!(canonical.point(), non_canonical.point());
+        assert_eq!(non_canonical.to_bytes(), encode(y, false));
+        assert_ne!(canonical, non_canonical);
+    }
+
+    #[test]
+    fn test_lr_schnorr_deterministic() {
+        let params = Params::new(20);
+        let sk: Vec<Scalar> = (1..=20u64).map(Scalar::from).collect();
+        let sk = SigningKey::from(sk);
+        let pk = Schnorr::public_key(&params, &sk);
+        let msg = b"Hello, world!";
+        let signature = Schnorr::sign_deterministic(&params, &sk, &pk, msg);
+        assert!(Schnorr::verify(&params, &pk, msg, &signature));
+        assert_eq!(Schnorr::sign_deterministic(&params, &sk, &pk, msg), signature);
+        let hex: String = signature.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "e8760feaf836f66c031f1f9893d8cf78b4a032fe73f268da325575db17ff5c01d3e0453ee648b6f6c04a5975cde34d43a0cc3607eee3f1b37738a13282caed04");
+
+        let hedged = Schnorr::sign_hedged(&params, &sk, &pk, msg);
+        assert!(Schnorr::verify(&params, &pk, msg, &hedged));
+        assert_ne!(hedged, signature);
+    }
+}
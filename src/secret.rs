@@ -0,0 +1,132 @@
+//! Storage for secret vectors.
+//!
+//! `SecretVec` owns its buffer and overwrites every element when it is
+//! dropped. With the `mlock` feature the buffer is also given whole pages of
+//! its own and locked into RAM, so that it is never written to swap. Locking
+//! is best effort: when `RLIMIT_MEMLOCK` is exhausted the vector is still
+//! usable and still wiped, only not locked.
+
+use std::alloc::{self, Layout};
+use std::ops::Deref;
+use std::ptr::NonNull;
+use zeroize::Zeroize;
+
+pub(crate) struct SecretVec<T: Copy + Zeroize> {
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+// `SecretVec` owns its elements like a `Vec` does.
+unsafe impl<T: Copy + Zeroize + Send> Send for SecretVec<T> {}
+unsafe impl<T: Copy + Zeroize + Sync> Sync for SecretVec<T> {}
+
+impl<T: Copy + Zeroize> SecretVec<T> {
+    fn layout(len: usize) -> Layout {
+        let layout = Layout::array::<T>(len).expect("secret vector too long");
+        #[cfg(feature = "mlock")]
+        let layout = {
+            let page = page_size().max(layout.align());
+            let size = layout.size().div_ceil(page) * page;
+            Layout::from_size_align(size, page).expect("secret vector too long")
+        };
+        layout
+    }
+
+    pub(crate) fn from_slice(elements: &[T]) -> Self {
+        let len = elements.len();
+        if len == 0 || std::mem::size_of::<T>() == 0 {
+            return SecretVec { ptr: NonNull::dangling(), len };
+        }
+        let layout = Self::layout(len);
+        // Safety: `layout` has a non-zero size, and the new buffer has room
+        // for `len` elements that do not overlap `elements`.
+        let ptr = unsafe {
+            let ptr = alloc::alloc(layout) as *mut T;
+            let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+            std::ptr::copy_nonoverlapping(elements.as_ptr(), ptr.as_ptr(), len);
+            ptr
+        };
+        #[cfg(feature = "mlock")]
+        unsafe {
+            libc::mlock(ptr.as_ptr() as *const libc::c_void, layout.size());
+        }
+        SecretVec { ptr, len }
+    }
+
+    /// Moves the elements out into a plain `Vec`, which is no longer wiped on drop.
+    pub(crate) fn into_vec(self) -> Vec<T> {
+        self.to_vec()
+    }
+}
+
+#[cfg(feature = "mlock")]
+fn page_size() -> usize {
+    // Safety: `sysconf` has no preconditions.
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page > 0 { page as usize } else { 4096 }
+}
+
+impl<T: Copy + Zeroize> Drop for SecretVec<T> {
+    fn drop(&mut self) {
+        if self.len == 0 || std::mem::size_of::<T>() == 0 {
+            return;
+        }
+        // Safety: `ptr` holds `len` initialized elements allocated with
+        // `layout(len)`, and nothing else refers to them any more.
+        unsafe {
+            std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len).iter_mut().for_each(Zeroize::zeroize);
+            let layout = Self::layout(self.len);
+            #[cfg(feature = "mlock")]
+            libc::munlock(self.ptr.as_ptr() as *const libc::c_void, layout.size());
+            alloc::dealloc(self.ptr.as_ptr() as *mut u8, layout);
+        }
+    }
+}
+
+impl<T: Copy + Zeroize> Deref for SecretVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // Safety: `ptr` holds `len` initialized elements, or is dangling with
+        // `len == 0`.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy + Zeroize> From<Vec<T>> for SecretVec<T> {
+    fn from(mut elements: Vec<T>) -> Self {
+        let secret = SecretVec::from_slice(&elements);
+        elements.zeroize();
+        secret
+    }
+}
+
+impl<T: Copy + Zeroize> Clone for SecretVec<T> {
+    fn clone(&self) -> Self {
+        SecretVec::from_slice(self)
+    }
+}
+
+impl<T: Copy + Zeroize + PartialEq> PartialEq for SecretVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Copy + Zeroize + Eq> Eq for SecretVec<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn test_secret_vec() {
+        let elements: Vec<Scalar> = (0..100u64).map(Scalar::from).collect();
+        let secret = SecretVec::from(elements.clone());
+        assert_eq!(&*secret, elements.as_slice());
+        assert!(secret.clone() == secret);
+        assert_eq!(secret.into_vec(), elements);
+        assert!(SecretVec::<Scalar>::from(Vec::new()).is_empty());
+    }
+}